@@ -1,21 +1,45 @@
-use std::ops::Div;
-use std::ops::Sub;
 use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Arc;
 
-use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
 
 use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::oneshot;
-use tokio::time::{self, Duration};
+use tokio::time::{self, Duration, Instant};
 
-use crate::game::{FrozenGameObject, GameMessage};
+use tracing::Instrument;
+
+use crate::behavior::{ActorBehavior, Chase, Flee, Flock, Perception, Wander};
+use crate::fsm::{FsmBehavior, FsmDefinition};
+use crate::game::GameMessage;
+use crate::metrics::METRICS;
+use crate::pathfinding::Path;
 
 static ACTOR_COUNTER: AtomicI32 = AtomicI32::new(1);
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ActorType {
     Walker,
-    Bullet,
+    Wanderer,
+    Hunter,
+    Critter,
+    Guard,
+}
+
+impl ActorType {
+    /// Runs `definition` if the type has been given one, otherwise its built-in behavior
+    pub fn behavior(&self, definition: Option<Arc<FsmDefinition>>) -> Box<dyn ActorBehavior> {
+        if let Some(definition) = definition {
+            return Box::new(FsmBehavior::new(definition));
+        }
+        match self {
+            ActorType::Walker => Box::new(Flock),
+            ActorType::Wanderer => Box::new(Wander::default()),
+            ActorType::Hunter => Box::new(Chase::default()),
+            ActorType::Critter => Box::new(Flee::default()),
+            ActorType::Guard => Box::new(FsmBehavior::new(FsmDefinition::guard())),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -33,105 +57,61 @@ impl Actor {
             object_id,
         }
     }
-}
 
-fn compute_alignment(actor: &FrozenGameObject, others: &Vec<FrozenGameObject>) -> Vector3<f32> {
-    let mut average_velocity = Vector3::new(0.0, 0.0, 0.0);
-    let len = others.len();
-    if len == 0 {
-        return average_velocity;
-    }
-    for i in others {
-        average_velocity += i.velocity;
-    }
-    average_velocity = average_velocity.div(len as f32);
-    average_velocity.sub(actor.velocity) / 2.5
-}
-
-fn compute_cohesion(actor: &FrozenGameObject, others: &Vec<FrozenGameObject>) -> Vector3<f32> {
-    let len = others.len();
-
-    let mut average_position = Vector3::new(0.0, 0.0, 0.0);
-    if len == 0 {
-        return average_position;
-    }
-
-    for i in others {
-        average_position += i.position;
-    }
-    average_position = average_position.div(len as f32);
-    average_position.sub(actor.position) / 50.0
-}
-
-fn compute_separation(actor: &FrozenGameObject, others: &Vec<FrozenGameObject>) -> Vector3<f32> {
-    let mut separation = Vector3::new(0.0, 0.0, 0.0);
-    for i in others {
-        let distance = actor.position.metric_distance(&i.position);
-        if distance == 0.0 {
-            continue;
+    /// Brings back a saved actor, making sure new actors never reuse its id
+    pub fn restore(actor_id: u32, actor_type: ActorType, object_id: u32) -> Actor {
+        ACTOR_COUNTER.fetch_max(actor_id as i32 + 1, Ordering::Relaxed);
+        Actor {
+            actor_id,
+            actor_type,
+            object_id,
         }
-        let difference_vec = i
-            .position
-            .sub(actor.position)
-            .div(actor.position.metric_distance(&i.position) * 2.0);
-        separation -= difference_vec;
-    }
-    separation * 2.5
-}
-
-fn compute_attack(actor: &FrozenGameObject, players: &Vec<FrozenGameObject>) -> Vector3<f32> {
-    if players.len() == 0 {
-        return Vector3::new(0.0, 0.0, 0.0);
-    }
-
-    let mut average_velocity = Vector3::new(0.0, 0.0, 0.0);
-    let mut average_position = Vector3::new(0.0, 0.0, 0.0);
-
-    for player in players {
-        average_position += player.position;
-        average_velocity += player.velocity;
     }
-
-    average_position /= players.len() as f32;
-    average_velocity /= players.len() as f32;
-
-    (average_velocity.sub(actor.velocity) + (average_position.sub(actor.position))) / 25.0
 }
 
+#[tracing::instrument(
+    name = "actor",
+    skip_all,
+    fields(actor_id = actor.actor_id, object_id = actor.object_id, actor_type = ?actor.actor_type)
+)]
 pub async fn actor_main(
     actor: Actor,
+    definition: Option<Arc<FsmDefinition>>,
     tx: UnboundedSender<GameMessage>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mut interval = time::interval(Duration::from_millis(100));
+    let mut behavior = actor.actor_type.behavior(definition);
+
+    let mut think_interval = Duration::from_millis(100);
+    let mut interval = time::interval(think_interval);
     loop {
         interval.tick().await;
 
-        let (sender, receiver) = oneshot::channel::<(
-            Actor,
-            FrozenGameObject,
-            Vec<FrozenGameObject>,
-            Vec<FrozenGameObject>,
-        )>();
+        let (sender, receiver) = oneshot::channel::<Perception>();
+        let scan_started = Instant::now();
         tx.send(GameMessage::Scan(actor.actor_id, sender))?;
-        let (_, actor_obj, players, actors) = receiver.await?;
-
-        let alignment = compute_alignment(&actor_obj, &actors);
-        let cohesion = compute_cohesion(&actor_obj, &actors);
-        let separation = compute_separation(&actor_obj, &actors);
-        let attack = compute_attack(&actor_obj, &players);
+        let perception = receiver.instrument(tracing::trace_span!("scan")).await?;
+        METRICS.scan_latency.observe(scan_started.elapsed());
+
+        // pick up retuned think rates from config reloads
+        let params_interval = Duration::from_millis(perception.params.think_interval_ms.max(1));
+        if params_interval != think_interval {
+            think_interval = params_interval;
+            interval = time::interval_at(time::Instant::now() + think_interval, think_interval);
+        }
 
-        let dir = alignment + cohesion + separation + attack;
+        behavior.perceive(&perception);
 
-        // log::debug!("align: {:?} | cohe: {:?} | sep: {:?} -> {:?}", alignment, cohesion, separation, dir);
+        if let Some(target) = behavior.path_request(&perception) {
+            let (sender, receiver) = oneshot::channel::<Option<Path>>();
+            tx.send(GameMessage::FindPath(actor.actor_id, target, sender))?;
+            behavior.receive_path(receiver.await?);
+        }
 
-        if !(dir.x.is_nan() && dir.y.is_nan() && dir.z.is_nan()) {
-            //log::debug!("dir: {:?}", dir);
-            tx.send(GameMessage::ActorMove(
-                actor.actor_id,
-                dir.x as f32,
-                dir.y as f32,
-                dir.z as f32,
-            ))?;
+        if let Some(dir) = behavior.decide(&perception) {
+            if !(dir.x.is_nan() && dir.y.is_nan() && dir.z.is_nan()) {
+                //tracing::debug!("dir: {:?}", dir);
+                behavior.act(&actor, dir, &tx)?;
+            }
         }
     }
 }
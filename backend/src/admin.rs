@@ -0,0 +1,226 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+
+use warp::http::StatusCode;
+use warp::Filter;
+
+use crate::actor::ActorType;
+use crate::game::GameMessage;
+use crate::mode::{ModeKind, RoundState};
+
+#[derive(Debug, Deserialize)]
+pub enum AdminCommand {
+    ListPlayers,
+    Kick(u32, Option<String>),
+    Ban(u32, Option<String>),
+    Notice(String),
+    /// Spawns where the spawner picks unless given a position
+    SpawnActor(ActorType, Option<(f32, f32, f32)>),
+    DespawnActor(u32),
+    SetMode(ModeKind),
+    Pause,
+    Resume,
+    Stats,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerInfo {
+    pub client_id: u32,
+    pub addr: String,
+    pub username: String,
+    pub object_id: u32,
+    pub alive: bool,
+    pub team: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AreaStats {
+    pub ticks: u32,
+    pub tps: usize,
+    pub paused: bool,
+    pub players: usize,
+    pub spectators: usize,
+    pub actors: usize,
+    pub objects: usize,
+    pub entities: usize,
+    pub mode: Option<ModeKind>,
+    pub round: u32,
+    pub round_state: RoundState,
+}
+
+#[derive(Debug, Serialize)]
+pub enum AdminReply {
+    Ok,
+    Players(Vec<PlayerInfo>),
+    Spawned(u32),
+    Stats(AreaStats),
+    Error(String),
+}
+
+/// Compares every byte of the longer input, including the length difference, so timing
+/// leaks neither the secret nor its length
+fn secret_matches(secret: &str, given: &str) -> bool {
+    let (secret, given) = (secret.as_bytes(), given.as_bytes());
+    let diff = (0..secret.len().max(given.len())).fold(secret.len() ^ given.len(), |diff, i| {
+        let a = secret.get(i).copied().unwrap_or(0);
+        let b = given.get(i).copied().unwrap_or(0);
+        diff | (a ^ b) as usize
+    });
+    diff == 0
+}
+
+async fn handle_command(
+    secret: Arc<Option<String>>,
+    authorization: Option<String>,
+    command: AdminCommand,
+    game_conn: UnboundedSender<GameMessage>,
+) -> Result<impl warp::Reply, Infallible> {
+    let given = authorization
+        .as_deref()
+        .and_then(|header| header.strip_prefix("Bearer "));
+    let authorized = match (secret.as_deref(), given) {
+        (Some(secret), Some(given)) => secret_matches(secret, given),
+        _ => false,
+    };
+    if !authorized {
+        tracing::warn!("rejected admin command {:?}", command);
+        let reply = AdminReply::Error("Unauthorized".to_string());
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&reply),
+            StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    tracing::info!("admin command {:?}", command);
+    let (reply_tx, reply_rx) = oneshot::channel();
+    if let Err(e) = game_conn.send(GameMessage::Admin(command, reply_tx)) {
+        tracing::error!("error sending admin command: {}", e);
+    }
+
+    let (reply, status) = match reply_rx.await {
+        Ok(reply) => (reply, StatusCode::OK),
+        Err(_) => (
+            AdminReply::Error("Game not responding".to_string()),
+            StatusCode::SERVICE_UNAVAILABLE,
+        ),
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&reply), status))
+}
+
+/// POST /admin taking a JSON command, authorized with `Bearer <secret>`
+pub fn admin_route(
+    secret: Option<String>,
+    game_tx: UnboundedSender<GameMessage>,
+) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    if secret.is_none() {
+        tracing::warn!("no admin secret set, admin commands will be refused");
+    }
+    let secret = Arc::new(secret);
+
+    warp::path("admin")
+        .and(warp::post())
+        .and(warp::any().map(move || secret.clone()))
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::body::json())
+        .and(warp::any().map(move || game_tx.clone()))
+        .and_then(handle_command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+
+    fn route(
+        secret: Option<&str>,
+    ) -> (
+        impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone,
+        UnboundedReceiver<GameMessage>,
+    ) {
+        let (game_tx, game_rx) = unbounded_channel();
+        (admin_route(secret.map(String::from), game_tx), game_rx)
+    }
+
+    #[test]
+    fn secrets_must_match_exactly() {
+        assert!(secret_matches("hunter2", "hunter2"));
+        assert!(!secret_matches("hunter2", "hunter3"));
+        assert!(!secret_matches("hunter2", "hunter"));
+        assert!(!secret_matches("hunter2", "hunter22"));
+        assert!(!secret_matches("hunter2", ""));
+    }
+
+    #[tokio::test]
+    async fn commands_without_the_secret_are_refused() {
+        let (route, mut game_rx) = route(Some("hunter2"));
+        for authorization in [None, Some("Bearer hunter3"), Some("hunter2")] {
+            let mut request = warp::test::request()
+                .method("POST")
+                .path("/admin")
+                .body(r#""Pause""#);
+            if let Some(authorization) = authorization {
+                request = request.header("authorization", authorization);
+            }
+            let response = request.reply(&route).await;
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        assert!(game_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn commands_are_refused_when_no_secret_is_set() {
+        let (route, _game_rx) = route(None);
+        let response = warp::test::request()
+            .method("POST")
+            .path("/admin")
+            .header("authorization", "Bearer ")
+            .body(r#""Pause""#)
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn authorized_commands_are_parsed_and_passed_to_the_game() {
+        let (route, mut game_rx) = route(Some("hunter2"));
+        tokio::spawn(async move {
+            if let Some(GameMessage::Admin(command, reply_tx)) = game_rx.recv().await {
+                let reply = match command {
+                    AdminCommand::Kick(3, Some(reason)) if reason == "spam" => AdminReply::Ok,
+                    command => AdminReply::Error(format!("{:?}", command)),
+                };
+                let _ = reply_tx.send(reply);
+            }
+        });
+
+        let response = warp::test::request()
+            .method("POST")
+            .path("/admin")
+            .header("authorization", "Bearer hunter2")
+            .body(r#"{"Kick": [3, "spam"]}"#)
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), r#""Ok""#);
+    }
+
+    #[tokio::test]
+    async fn unknown_commands_are_rejected() {
+        let (route, mut game_rx) = route(Some("hunter2"));
+        let response = warp::test::request()
+            .method("POST")
+            .path("/admin")
+            .header("authorization", "Bearer hunter2")
+            .body(r#"{"Explode": []}"#)
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(game_rx.try_recv().is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use serde::Serialize;
+
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::oneshot;
+
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+use crate::game::GameMessage;
+use crate::mode::{ModeKind, RoundState};
+use crate::terrain::{render_terrain_png, TerrainType};
+
+#[derive(Debug, Serialize)]
+pub struct ServerStatus {
+    pub uptime_ms: u64,
+    pub tps: usize,
+    pub paused: bool,
+    pub players: usize,
+    pub spectators: usize,
+    pub actors: usize,
+    pub mode: Option<ModeKind>,
+    pub round_state: RoundState,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerSummary {
+    pub username: String,
+    pub object_id: u32,
+    pub alive: bool,
+    pub team: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorldInfo {
+    pub seed: u32,
+    pub size: u32,
+    pub biomes: HashMap<TerrainType, usize>,
+}
+
+/// Sends a request to the game task and waits for its answer
+async fn ask<T>(
+    game_conn: &UnboundedSender<GameMessage>,
+    message: impl FnOnce(oneshot::Sender<T>) -> GameMessage,
+) -> Option<T> {
+    let (response_tx, response_rx) = oneshot::channel();
+    if let Err(e) = game_conn.send(message(response_tx)) {
+        tracing::error!("error sending api request: {}", e);
+        return None;
+    }
+    response_rx.await.ok()
+}
+
+fn unavailable() -> Response {
+    warp::reply::with_status("Game not responding", StatusCode::SERVICE_UNAVAILABLE).into_response()
+}
+
+fn json_reply<T: Serialize>(value: Option<T>) -> Result<Response, Infallible> {
+    Ok(match value {
+        Some(value) => warp::reply::json(&value).into_response(),
+        None => unavailable(),
+    })
+}
+
+async fn get_status(game_conn: UnboundedSender<GameMessage>) -> Result<Response, Infallible> {
+    json_reply(ask(&game_conn, GameMessage::Status).await)
+}
+
+async fn get_players(game_conn: UnboundedSender<GameMessage>) -> Result<Response, Infallible> {
+    json_reply(ask(&game_conn, GameMessage::Players).await)
+}
+
+async fn get_world(game_conn: UnboundedSender<GameMessage>) -> Result<Response, Infallible> {
+    json_reply(ask(&game_conn, GameMessage::World).await)
+}
+
+async fn get_terrain_png(game_conn: UnboundedSender<GameMessage>) -> Result<Response, Infallible> {
+    let (size, terrain_map) = match ask(&game_conn, GameMessage::TerrainMap).await {
+        Some(terrain) => terrain,
+        None => return Ok(unavailable()),
+    };
+
+    // encoding takes a while, keep it off the async workers
+    let png = tokio::task::spawn_blocking(move || render_terrain_png(size, &terrain_map)).await;
+    Ok(match png {
+        Ok(Ok(png)) => warp::reply::with_header(png, "content-type", "image/png").into_response(),
+        Ok(Err(e)) => {
+            tracing::error!("error rendering terrain: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(e) => {
+            tracing::error!("error rendering terrain: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    })
+}
+
+/// Read-only GET endpoints for server browsers and dashboards
+pub fn api_routes(
+    game_tx: UnboundedSender<GameMessage>,
+) -> impl Filter<Extract = (Response,), Error = warp::Rejection> + Clone {
+    let game_conn = warp::any().map(move || game_tx.clone());
+
+    let status = warp::path("status")
+        .and(warp::path::end())
+        .and(game_conn.clone())
+        .and_then(get_status);
+    let players = warp::path("players")
+        .and(warp::path::end())
+        .and(game_conn.clone())
+        .and_then(get_players);
+    let world = warp::path("world")
+        .and(warp::path::end())
+        .and(game_conn.clone())
+        .and_then(get_world);
+    let terrain = warp::path("terrain.png")
+        .and(warp::path::end())
+        .and(game_conn)
+        .and_then(get_terrain_png);
+
+    warp::get().and(
+        status
+            .or(players)
+            .unify()
+            .or(world)
+            .unify()
+            .or(terrain)
+            .unify(),
+    )
+}
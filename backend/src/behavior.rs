@@ -0,0 +1,284 @@
+use std::ops::Div;
+use std::ops::Sub;
+
+use nalgebra::Vector3;
+
+use rand::Rng;
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::actor::Actor;
+use crate::config::FlockingParams;
+use crate::game::{FrozenGameObject, GameMessage};
+use crate::pathfinding::{Path, PathFollower};
+
+/// Beyond this distance chasers follow a terrain path rather than beelining
+const PATH_RANGE: f32 = 30.0;
+
+/// Snapshot of the world around an actor, as gathered by the game on a scan
+#[derive(Clone, Debug)]
+pub struct Perception {
+    pub actor: Actor,
+    pub object: FrozenGameObject,
+    pub players: Vec<FrozenGameObject>,
+    pub actors: Vec<FrozenGameObject>,
+    pub params: FlockingParams,
+    /// Flow field direction toward the nearest player, when one has been built
+    pub flow: Option<Vector3<f32>>,
+}
+
+impl Perception {
+    pub fn nearest_player(&self) -> Option<&FrozenGameObject> {
+        self.players.iter().min_by(|a, b| {
+            let dist_a = a.position.metric_distance(&self.object.position);
+            let dist_b = b.position.metric_distance(&self.object.position);
+            dist_a
+                .partial_cmp(&dist_b)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+    }
+}
+
+/// Drives an actor each think step: take in a perception, pick a steering
+/// direction and then apply it
+pub trait ActorBehavior: Send {
+    fn perceive(&mut self, _perception: &Perception) {}
+
+    fn decide(&mut self, perception: &Perception) -> Option<Vector3<f32>>;
+
+    /// Where this behavior wants a terrain-aware path to, if anywhere
+    fn path_request(&mut self, _perception: &Perception) -> Option<Vector3<f32>> {
+        None
+    }
+
+    fn receive_path(&mut self, _path: Option<Path>) {}
+
+    fn act(
+        &mut self,
+        actor: &Actor,
+        steering: Vector3<f32>,
+        tx: &UnboundedSender<GameMessage>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        tx.send(GameMessage::ActorMove(
+            actor.actor_id,
+            steering.x,
+            steering.y,
+            steering.z,
+        ))?;
+        Ok(())
+    }
+}
+
+fn compute_alignment(
+    actor: &FrozenGameObject,
+    others: &Vec<FrozenGameObject>,
+    params: &FlockingParams,
+) -> Vector3<f32> {
+    let mut average_velocity = Vector3::new(0.0, 0.0, 0.0);
+    let len = others.len();
+    if len == 0 {
+        return average_velocity;
+    }
+    for i in others {
+        average_velocity += i.velocity;
+    }
+    average_velocity = average_velocity.div(len as f32);
+    average_velocity.sub(actor.velocity) / params.alignment_divisor
+}
+
+fn compute_cohesion(
+    actor: &FrozenGameObject,
+    others: &Vec<FrozenGameObject>,
+    params: &FlockingParams,
+) -> Vector3<f32> {
+    let len = others.len();
+
+    let mut average_position = Vector3::new(0.0, 0.0, 0.0);
+    if len == 0 {
+        return average_position;
+    }
+
+    for i in others {
+        average_position += i.position;
+    }
+    average_position = average_position.div(len as f32);
+    average_position.sub(actor.position) / params.cohesion_divisor
+}
+
+fn compute_separation(
+    actor: &FrozenGameObject,
+    others: &Vec<FrozenGameObject>,
+    params: &FlockingParams,
+) -> Vector3<f32> {
+    let mut separation = Vector3::new(0.0, 0.0, 0.0);
+    for i in others {
+        let distance = actor.position.metric_distance(&i.position);
+        if distance == 0.0 {
+            continue;
+        }
+        let difference_vec = i
+            .position
+            .sub(actor.position)
+            .div(actor.position.metric_distance(&i.position) * 2.0);
+        separation -= difference_vec;
+    }
+    separation * params.separation_factor
+}
+
+fn compute_attack(
+    actor: &FrozenGameObject,
+    players: &Vec<FrozenGameObject>,
+    flow: Option<Vector3<f32>>,
+    params: &FlockingParams,
+) -> Vector3<f32> {
+    if players.len() == 0 {
+        return Vector3::new(0.0, 0.0, 0.0);
+    }
+
+    let mut average_velocity = Vector3::new(0.0, 0.0, 0.0);
+    let mut average_position = Vector3::new(0.0, 0.0, 0.0);
+
+    for player in players {
+        average_position += player.position;
+        average_velocity += player.velocity;
+    }
+
+    average_position /= players.len() as f32;
+    average_velocity /= players.len() as f32;
+
+    // follow the flow field around obstacles, keeping the pull of the straight line
+    let mut approach = average_position.sub(actor.position);
+    if let Some(flow) = flow {
+        approach = flow * approach.magnitude();
+    }
+
+    (average_velocity.sub(actor.velocity) + approach) / params.attack_divisor
+}
+
+/// Boids: align, cohere and separate with nearby actors while converging on players
+#[derive(Default)]
+pub struct Flock;
+
+impl ActorBehavior for Flock {
+    fn decide(&mut self, perception: &Perception) -> Option<Vector3<f32>> {
+        let actor_obj = &perception.object;
+
+        let params = &perception.params;
+
+        let alignment = compute_alignment(actor_obj, &perception.actors, params);
+        let cohesion = compute_cohesion(actor_obj, &perception.actors, params);
+        let separation = compute_separation(actor_obj, &perception.actors, params);
+        let attack = compute_attack(actor_obj, &perception.players, perception.flow, params);
+
+        // tracing::debug!("align: {:?} | cohe: {:?} | sep: {:?}", alignment, cohesion, separation);
+
+        Some(alignment + cohesion + separation + attack)
+    }
+}
+
+/// Drift around aimlessly, nudging the heading a little every step
+pub struct Wander {
+    heading: f32,
+}
+
+impl Default for Wander {
+    fn default() -> Wander {
+        let mut rng = rand::thread_rng();
+        Wander {
+            heading: rng.gen::<f32>() * std::f32::consts::TAU,
+        }
+    }
+}
+
+impl Wander {
+    fn steer(&mut self, actor: &FrozenGameObject) -> Vector3<f32> {
+        let mut rng = rand::thread_rng();
+        self.heading += (rng.gen::<f32>() - 0.5) * 0.5;
+
+        let desired = Vector3::new(self.heading.cos(), 0.0, self.heading.sin());
+        (desired - actor.velocity) / 10.0
+    }
+}
+
+impl ActorBehavior for Wander {
+    fn decide(&mut self, perception: &Perception) -> Option<Vector3<f32>> {
+        Some(self.steer(&perception.object))
+    }
+}
+
+/// Run down the nearest player, keeping some distance from other actors and
+/// pathing around terrain when the target is far off
+#[derive(Default)]
+pub struct Chase {
+    wander: Wander,
+    follower: PathFollower,
+}
+
+impl ActorBehavior for Chase {
+    fn path_request(&mut self, perception: &Perception) -> Option<Vector3<f32>> {
+        let target = perception.nearest_player()?;
+        let distance = target.position.metric_distance(&perception.object.position);
+        if distance > PATH_RANGE && self.follower.needs_path(&target.position) {
+            return Some(self.follower.request(target.position));
+        }
+        None
+    }
+
+    fn receive_path(&mut self, path: Option<Path>) {
+        self.follower.set_path(path);
+    }
+
+    fn decide(&mut self, perception: &Perception) -> Option<Vector3<f32>> {
+        let actor_obj = &perception.object;
+        let params = &perception.params;
+
+        let target = match perception.nearest_player() {
+            Some(target) => target,
+            None => {
+                self.follower.clear();
+                return Some(self.wander.steer(actor_obj));
+            }
+        };
+
+        let separation = compute_separation(actor_obj, &perception.actors, params);
+
+        let distance = target.position.metric_distance(&actor_obj.position);
+        if distance > PATH_RANGE {
+            if let Some(steering) = self.follower.steer(actor_obj, params.attack_divisor) {
+                return Some(steering + separation);
+            }
+        }
+
+        // lead the target a little by aiming at where it is heading
+        let seek =
+            (target.position + target.velocity).sub(actor_obj.position) / params.attack_divisor;
+
+        Some(seek + separation)
+    }
+}
+
+/// Keep away from players, wandering when none are around
+#[derive(Default)]
+pub struct Flee {
+    wander: Wander,
+}
+
+impl ActorBehavior for Flee {
+    fn decide(&mut self, perception: &Perception) -> Option<Vector3<f32>> {
+        let actor_obj = &perception.object;
+
+        let threat = match perception.nearest_player() {
+            Some(threat) => threat,
+            None => return Some(self.wander.steer(actor_obj)),
+        };
+
+        let away = actor_obj.position.sub(threat.position);
+        let distance = away.magnitude();
+        if distance == 0.0 {
+            return Some(self.wander.steer(actor_obj));
+        }
+
+        // push harder the closer the threat gets
+        Some(away.normalize() * (10.0 / distance).min(1.0))
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::Instant;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChatChannel {
+    Global,
+    Team,
+    Proximity,
+    /// Private message to the player with this username
+    Whisper(String),
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChatMessage {
+    pub channel: ChatChannel,
+    pub object_id: u32,
+    pub username: String,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatConfig {
+    pub max_length: usize,
+    /// Messages a client can send in a burst before being rate limited
+    pub burst: f32,
+    /// Messages per second a client's allowance refills at
+    pub refill_per_second: f32,
+    pub proximity_range: f32,
+    pub muted: Vec<String>,
+    pub blocked_words: Vec<String>,
+}
+
+impl Default for ChatConfig {
+    fn default() -> ChatConfig {
+        ChatConfig {
+            max_length: 200,
+            burst: 5.0,
+            refill_per_second: 1.0,
+            proximity_range: 50.0,
+            muted: Vec::new(),
+            blocked_words: Vec::new(),
+        }
+    }
+}
+
+/// Moderation step every message passes through, returning the text to send
+/// (possibly rewritten) or why it was refused
+pub trait ChatFilter: Send {
+    fn filter(&self, username: &str, channel: &ChatChannel, text: String)
+        -> Result<String, String>;
+
+    /// Picks up a reloaded config, for filters that are built from it
+    fn set_config(&mut self, _config: &ChatConfig) {}
+}
+
+/// Refuses everything from muted usernames
+#[derive(Default)]
+pub struct MuteList {
+    muted: Vec<String>,
+}
+
+impl ChatFilter for MuteList {
+    fn filter(
+        &self,
+        username: &str,
+        _channel: &ChatChannel,
+        text: String,
+    ) -> Result<String, String> {
+        if self
+            .muted
+            .iter()
+            .any(|muted| muted.eq_ignore_ascii_case(username))
+        {
+            return Err("You are muted".to_string());
+        }
+        Ok(text)
+    }
+
+    fn set_config(&mut self, config: &ChatConfig) {
+        self.muted = config.muted.clone();
+    }
+}
+
+/// Stars out blocked words wherever they appear
+#[derive(Default)]
+pub struct WordFilter {
+    blocked_words: Vec<String>,
+}
+
+impl ChatFilter for WordFilter {
+    fn filter(
+        &self,
+        _username: &str,
+        _channel: &ChatChannel,
+        text: String,
+    ) -> Result<String, String> {
+        let filtered = text
+            .split(' ')
+            .map(|word| {
+                let bare = word.trim_matches(|c: char| !c.is_alphanumeric());
+                if self
+                    .blocked_words
+                    .iter()
+                    .any(|blocked| blocked.eq_ignore_ascii_case(bare))
+                {
+                    "*".repeat(word.chars().count())
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(" ");
+        Ok(filtered)
+    }
+
+    fn set_config(&mut self, config: &ChatConfig) {
+        self.blocked_words = config.blocked_words.clone();
+    }
+}
+
+struct Allowance {
+    tokens: f32,
+    updated_at: Instant,
+}
+
+/// Checks outgoing chat against length limits, rate limits and filters
+pub struct Chat {
+    pub config: ChatConfig,
+    filters: Vec<Box<dyn ChatFilter>>,
+    allowances: HashMap<u32, Allowance>,
+}
+
+impl Chat {
+    pub fn new(config: &ChatConfig) -> Chat {
+        let mut chat = Chat {
+            config: config.clone(),
+            filters: Vec::new(),
+            allowances: HashMap::new(),
+        };
+        chat.add_filter(Box::new(MuteList::default()));
+        chat.add_filter(Box::new(WordFilter::default()));
+        chat
+    }
+
+    /// Replaces the config, passing it on to every registered filter
+    pub fn set_config(&mut self, config: &ChatConfig) {
+        self.config = config.clone();
+        for filter in self.filters.iter_mut() {
+            filter.set_config(config);
+        }
+    }
+
+    /// Runs `filter` on every message after the ones already registered
+    pub fn add_filter(&mut self, mut filter: Box<dyn ChatFilter>) {
+        filter.set_config(&self.config);
+        self.filters.push(filter);
+    }
+
+    pub fn forget(&mut self, client_id: u32) {
+        self.allowances.remove(&client_id);
+    }
+
+    /// Text ready to deliver, or the reason the sender gets back instead
+    pub fn check(
+        &mut self,
+        client_id: u32,
+        username: &str,
+        channel: &ChatChannel,
+        text: String,
+    ) -> Result<String, String> {
+        let text = text.trim().to_string();
+        if text.is_empty() {
+            return Err("Message is empty".to_string());
+        }
+        if text.chars().count() > self.config.max_length {
+            return Err(format!(
+                "Message is longer than {} characters",
+                self.config.max_length
+            ));
+        }
+
+        let text = self
+            .filters
+            .iter()
+            .try_fold(text, |text, filter| filter.filter(username, channel, text))?;
+
+        let now = Instant::now();
+        let burst = self.config.burst.max(1.0);
+        let allowance = self.allowances.entry(client_id).or_insert(Allowance {
+            tokens: burst,
+            updated_at: now,
+        });
+        let refill = (now - allowance.updated_at).as_secs_f32() * self.config.refill_per_second;
+        allowance.tokens = (allowance.tokens + refill).min(burst);
+        allowance.updated_at = now;
+        if allowance.tokens < 1.0 {
+            return Err("Slow down".to_string());
+        }
+        allowance.tokens -= 1.0;
+        Ok(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::time::Duration;
+
+    fn chat(burst: f32, refill_per_second: f32) -> Chat {
+        Chat::new(&ChatConfig {
+            burst,
+            refill_per_second,
+            ..ChatConfig::default()
+        })
+    }
+
+    fn send(chat: &mut Chat, client_id: u32) -> Result<String, String> {
+        chat.check(client_id, "dino", &ChatChannel::Global, "hi".to_string())
+    }
+
+    #[test]
+    fn check_allows_a_burst_then_slows_down() {
+        let mut chat = chat(2.0, 0.0);
+
+        assert!(send(&mut chat, 1).is_ok());
+        assert!(send(&mut chat, 1).is_ok());
+        assert_eq!(send(&mut chat, 1), Err("Slow down".to_string()));
+    }
+
+    #[test]
+    fn check_limits_each_client_separately() {
+        let mut chat = chat(1.0, 0.0);
+
+        assert!(send(&mut chat, 1).is_ok());
+        assert!(send(&mut chat, 2).is_ok());
+        assert!(send(&mut chat, 1).is_err());
+    }
+
+    #[test]
+    fn check_refills_the_allowance_over_time() {
+        let mut chat = chat(1.0, 1.0);
+        assert!(send(&mut chat, 1).is_ok());
+        assert!(send(&mut chat, 1).is_err());
+
+        let allowance = chat.allowances.get_mut(&1).unwrap();
+        allowance.updated_at -= Duration::from_secs(2);
+        assert!(send(&mut chat, 1).is_ok());
+        assert!(send(&mut chat, 1).is_err());
+    }
+
+    #[test]
+    fn check_refuses_before_spending_allowance() {
+        let mut chat = chat(1.0, 0.0);
+        chat.set_config(&ChatConfig {
+            muted: vec!["Dino".to_string()],
+            ..chat.config.clone()
+        });
+
+        assert!(chat
+            .check(1, "rex", &ChatChannel::Global, "   ".to_string())
+            .is_err());
+        assert_eq!(send(&mut chat, 1), Err("You are muted".to_string()));
+        assert!(chat
+            .check(1, "rex", &ChatChannel::Global, "hi".to_string())
+            .is_ok());
+    }
+
+    /// Shouts everything, to tell it apart from the built-in filters
+    struct Shout;
+
+    impl ChatFilter for Shout {
+        fn filter(
+            &self,
+            _username: &str,
+            _channel: &ChatChannel,
+            text: String,
+        ) -> Result<String, String> {
+            Ok(text.to_uppercase())
+        }
+    }
+
+    #[test]
+    fn added_filters_survive_a_config_reload() {
+        let mut chat = chat(10.0, 0.0);
+        chat.add_filter(Box::new(Shout));
+        chat.set_config(&ChatConfig {
+            blocked_words: vec!["darn".to_string()],
+            ..chat.config.clone()
+        });
+
+        let text = chat.check(1, "dino", &ChatChannel::Global, "hi darn".to_string());
+        assert_eq!(text, Ok("HI ****".to_string()));
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::{Duration, Instant};
+
+use crate::actor::ActorType;
+use crate::effects::EffectKind;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DamageKind {
+    Projectile,
+    Melee,
+    Poison,
+}
+
+/// A single hit before armor, credited to the object that dealt it
+#[derive(Clone, Copy, Debug)]
+pub struct Damage {
+    pub source: u32,
+    pub kind: DamageKind,
+    pub amount: u8,
+}
+
+/// Flat reduction applied first, then a fractional resistance per damage kind
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Armor {
+    pub flat: u8,
+    pub resistances: HashMap<DamageKind, f32>,
+}
+
+impl Armor {
+    pub fn mitigate(&self, damage: &Damage) -> u8 {
+        let resistance = self
+            .resistances
+            .get(&damage.kind)
+            .cloned()
+            .unwrap_or(0.0)
+            .clamp(0.0, 1.0);
+        let amount = damage.amount.saturating_sub(self.flat) as f32 * (1.0 - resistance);
+        amount.round() as u8
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CombatConfig {
+    /// How long a target shrugs off further damage after being hit
+    pub invulnerability_ms: u64,
+    pub melee_range: f32,
+    pub melee_cooldown_ms: u64,
+    /// Contact damage per actor type, types not listed don't attack
+    pub melee_damage: HashMap<ActorType, u8>,
+    /// Status effect an actor type's melee hits leave on their target
+    pub melee_effects: HashMap<ActorType, EffectKind>,
+    pub player_armor: Armor,
+    pub armor: HashMap<ActorType, Armor>,
+}
+
+impl Default for CombatConfig {
+    fn default() -> CombatConfig {
+        CombatConfig {
+            invulnerability_ms: 200,
+            melee_range: 3.0,
+            melee_cooldown_ms: 1000,
+            melee_damage: HashMap::from([
+                (ActorType::Walker, 5),
+                (ActorType::Hunter, 10),
+                (ActorType::Guard, 15),
+            ]),
+            melee_effects: HashMap::from([
+                (ActorType::Hunter, EffectKind::Poison),
+                (ActorType::Guard, EffectKind::Slow),
+            ]),
+            player_armor: Armor::default(),
+            armor: HashMap::new(),
+        }
+    }
+}
+
+/// What a hit actually did once armor and invulnerability were accounted for
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    pub amount: u8,
+    pub health: u8,
+    pub killed: bool,
+}
+
+/// Applies the combat rules and keeps the timers they depend on
+pub struct Combat {
+    pub config: CombatConfig,
+    invulnerable_until: HashMap<u32, Instant>,
+    melee_ready_at: HashMap<u32, Instant>,
+}
+
+impl Combat {
+    pub fn new(config: &CombatConfig) -> Combat {
+        Combat {
+            config: config.clone(),
+            invulnerable_until: HashMap::new(),
+            melee_ready_at: HashMap::new(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: &CombatConfig) {
+        self.config = config.clone();
+    }
+
+    pub fn armor(&self, actor_type: Option<&ActorType>) -> Armor {
+        match actor_type {
+            Some(actor_type) => self
+                .config
+                .armor
+                .get(actor_type)
+                .cloned()
+                .unwrap_or_default(),
+            None => self.config.player_armor.clone(),
+        }
+    }
+
+    pub fn melee_damage(&self, actor_type: &ActorType) -> u8 {
+        self.config
+            .melee_damage
+            .get(actor_type)
+            .cloned()
+            .unwrap_or(0)
+    }
+
+    /// Damages a target with `health` left, `None` if it is dead or still invulnerable.
+    /// Poison ticks through invulnerability and doesn't grant any
+    pub fn resolve(
+        &mut self,
+        target_id: u32,
+        health: u8,
+        armor: &Armor,
+        damage: &Damage,
+    ) -> Option<Hit> {
+        if health == 0 {
+            return None;
+        }
+
+        let now = Instant::now();
+        let ticks_through = damage.kind == DamageKind::Poison;
+        if !ticks_through
+            && self
+                .invulnerable_until
+                .get(&target_id)
+                .is_some_and(|until| *until > now)
+        {
+            return None;
+        }
+
+        let amount = armor.mitigate(damage);
+        if amount == 0 {
+            return None;
+        }
+
+        if !ticks_through {
+            let invulnerability = Duration::from_millis(self.config.invulnerability_ms);
+            self.invulnerable_until
+                .insert(target_id, now + invulnerability);
+        }
+
+        let health = health.saturating_sub(amount);
+        Some(Hit {
+            amount,
+            health,
+            killed: health == 0,
+        })
+    }
+
+    /// Whether an attacker's melee is off cooldown, starting the cooldown if it is
+    pub fn melee_ready(&mut self, attacker_id: u32) -> bool {
+        let now = Instant::now();
+        if self
+            .melee_ready_at
+            .get(&attacker_id)
+            .is_some_and(|ready_at| *ready_at > now)
+        {
+            return false;
+        }
+
+        let cooldown = Duration::from_millis(self.config.melee_cooldown_ms);
+        self.melee_ready_at.insert(attacker_id, now + cooldown);
+        true
+    }
+
+    pub fn forget(&mut self, object_id: u32) {
+        self.invulnerable_until.remove(&object_id);
+        self.melee_ready_at.remove(&object_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(kind: DamageKind, amount: u8) -> Damage {
+        Damage {
+            source: 1,
+            kind,
+            amount,
+        }
+    }
+
+    #[test]
+    fn mitigate_subtracts_flat_armor_then_resistance() {
+        let armor = Armor {
+            flat: 5,
+            resistances: HashMap::from([(DamageKind::Projectile, 0.5)]),
+        };
+
+        assert_eq!(armor.mitigate(&hit(DamageKind::Projectile, 25)), 10);
+        assert_eq!(armor.mitigate(&hit(DamageKind::Melee, 25)), 20);
+    }
+
+    #[test]
+    fn mitigate_never_goes_below_zero() {
+        let armor = Armor {
+            flat: 30,
+            resistances: HashMap::new(),
+        };
+
+        assert_eq!(armor.mitigate(&hit(DamageKind::Melee, 25)), 0);
+    }
+
+    #[test]
+    fn mitigate_clamps_out_of_range_resistances() {
+        let armor = Armor {
+            flat: 0,
+            resistances: HashMap::from([(DamageKind::Projectile, 2.0), (DamageKind::Melee, -1.0)]),
+        };
+
+        assert_eq!(armor.mitigate(&hit(DamageKind::Projectile, 25)), 0);
+        assert_eq!(armor.mitigate(&hit(DamageKind::Melee, 25)), 25);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::time::{self, Duration};
+
+use crate::actor::ActorType;
+use crate::chat::ChatConfig;
+use crate::combat::CombatConfig;
+use crate::effects::EffectConfig;
+use crate::flowfield::FlowFieldConfig;
+use crate::fsm::BehaviorConfig;
+use crate::game::GameMessage;
+use crate::items::ItemConfig;
+use crate::mode::ModeConfig;
+use crate::moderation::ModerationConfig;
+use crate::pathfinding::PathfindingConfig;
+use crate::scoreboard::ScoreboardConfig;
+use crate::shutdown::ShutdownConfig;
+use crate::snapshot::SnapshotConfig;
+use crate::spawner::SpawnerConfig;
+use crate::spectator::SpectatorConfig;
+use crate::survival::SurvivalConfig;
+use crate::teams::TeamConfig;
+
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Knobs for the steering behaviors of a single actor type
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlockingParams {
+    pub alignment_divisor: f32,
+    pub cohesion_divisor: f32,
+    pub separation_factor: f32,
+    pub attack_divisor: f32,
+    pub think_interval_ms: u64,
+    pub player_detection_range: f32,
+    pub neighbor_limit: usize,
+}
+
+impl Default for FlockingParams {
+    fn default() -> FlockingParams {
+        FlockingParams {
+            alignment_divisor: 2.5,
+            cohesion_divisor: 50.0,
+            separation_factor: 2.5,
+            attack_divisor: 25.0,
+            think_interval_ms: 100,
+            player_detection_range: 100.0,
+            neighbor_limit: 20,
+        }
+    }
+}
+
+/// How long actors stay dead before they are cleared away and replaced
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LifecycleConfig {
+    pub corpse_linger_ms: u64,
+    pub respawn_delay_ms: u64,
+}
+
+impl Default for LifecycleConfig {
+    fn default() -> LifecycleConfig {
+        LifecycleConfig {
+            corpse_linger_ms: 5000,
+            respawn_delay_ms: 3000,
+        }
+    }
+}
+
+/// How long dead players wait and how far from enemies they come back
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RespawnConfig {
+    pub delay_ms: u64,
+    pub enemy_clearance: f32,
+}
+
+impl Default for RespawnConfig {
+    fn default() -> RespawnConfig {
+        RespawnConfig {
+            delay_ms: 5000,
+            enemy_clearance: 40.0,
+        }
+    }
+}
+
+/// Shots fired by players, speed in units per tick
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectileConfig {
+    pub speed: f32,
+    pub lifetime_ticks: u32,
+    pub damage: u8,
+    pub hit_radius: f32,
+    pub cooldown_ms: u64,
+    pub ammo_per_shot: u32,
+}
+
+impl Default for ProjectileConfig {
+    fn default() -> ProjectileConfig {
+        ProjectileConfig {
+            speed: 4.0,
+            lifetime_ticks: 90,
+            damage: 25,
+            hit_radius: 3.0,
+            cooldown_ms: 250,
+            ammo_per_shot: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub flocking: HashMap<ActorType, FlockingParams>,
+    pub behaviors: BehaviorConfig,
+    pub pathfinding: PathfindingConfig,
+    pub flow_fields: FlowFieldConfig,
+    pub lifecycle: LifecycleConfig,
+    pub spawner: SpawnerConfig,
+    pub projectiles: ProjectileConfig,
+    pub combat: CombatConfig,
+    pub respawn: RespawnConfig,
+    pub items: ItemConfig,
+    pub effects: EffectConfig,
+    pub survival: SurvivalConfig,
+    pub mode: ModeConfig,
+    pub teams: TeamConfig,
+    pub scoreboard: ScoreboardConfig,
+    pub chat: ChatConfig,
+    pub spectators: SpectatorConfig,
+    pub moderation: ModerationConfig,
+    pub shutdown: ShutdownConfig,
+    pub snapshot: SnapshotConfig,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, Box<dyn std::error::Error>> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Loads the config at `path`, falling back to defaults if it is missing or broken
+    pub fn load_or_default(path: &Path) -> Config {
+        match Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::warn!("using default config, could not load {:?}: {}", path, e);
+                Config::default()
+            }
+        }
+    }
+
+    pub fn flocking(&self, actor_type: &ActorType) -> FlockingParams {
+        self.flocking.get(actor_type).cloned().unwrap_or_default()
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Polls the config file and pushes a reload to the game whenever it changes
+pub async fn watch_config(path: PathBuf, tx: UnboundedSender<GameMessage>) {
+    let mut last_modified = modified_time(&path);
+    let mut interval = time::interval(WATCH_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let modified = modified_time(&path);
+        if modified.is_none() || modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+
+        match Config::load(&path) {
+            Ok(config) => {
+                tracing::info!("reloading config from {:?}", path);
+                if let Err(e) = tx.send(GameMessage::ReloadConfig(Box::new(config))) {
+                    tracing::error!("error sending config reload: {}", e);
+                    return;
+                }
+            }
+            Err(e) => {
+                tracing::error!("error reloading config {:?}: {}", path, e);
+            }
+        }
+    }
+}
@@ -17,6 +17,10 @@ impl BinLattice {
         (x as i32 / self.factor, y as i32 / self.factor)
     }
 
+    pub fn clear(&mut self) {
+        self.bins.clear();
+    }
+
     pub fn remove(&mut self, x: f32, y: f32, object_id: u32) {
         let key = self.key(x, y);
 
@@ -46,8 +50,8 @@ impl BinLattice {
         let max_y = (y + range / 2.0) as i32 / self.factor;
 
         let mut rv: Vec<u32> = Vec::new();
-        for i in min_x..max_x {
-            for j in min_y..max_y {
+        for i in min_x..=max_x {
+            for j in min_y..=max_y {
                 let key = (i, j);
                 if let Some(bin) = self.bins.get(&key) {
                     rv.append(&mut bin.clone());
@@ -55,8 +59,30 @@ impl BinLattice {
             }
         }
 
-        // log::debug!("scan from {},{} -> {},{} to {},{} found {}", x, y, min_x, min_y, max_x, max_y, rv.len());
+        // tracing::debug!("scan from {},{} -> {},{} to {},{} found {}", x, y, min_x, min_y, max_x, max_y, rv.len());
 
         rv
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearby_includes_the_bin_under_a_small_range() {
+        let mut lattice = BinLattice::new(50);
+        lattice.put(10.0, 10.0, 1);
+
+        assert_eq!(lattice.get_nearby(12.0, 12.0, 6.0), vec![1]);
+    }
+
+    #[test]
+    fn nearby_reaches_into_the_upper_neighbouring_bins() {
+        let mut lattice = BinLattice::new(50);
+        lattice.put(55.0, 55.0, 1);
+        lattice.put(120.0, 120.0, 2);
+
+        assert_eq!(lattice.get_nearby(45.0, 45.0, 30.0), vec![1]);
+    }
+}
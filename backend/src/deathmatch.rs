@@ -0,0 +1,99 @@
+use std::collections::HashMap;
+
+use crate::game::GameArea;
+use crate::mode::{GameMode, ModeKind, Outcome};
+use crate::teams::TeamConfig;
+
+/// Whoever is ahead once the round's time limit has run out, if anyone is
+fn time_up_leader(area: &GameArea, scores: &HashMap<u32, u32>) -> Option<Option<u32>> {
+    let limit = area.config.mode.time_limit_ms;
+    if limit == 0 || area.round.elapsed().as_millis() < limit as u128 {
+        return None;
+    }
+
+    let best = scores.values().max().cloned().unwrap_or(0);
+    let mut leaders = scores.iter().filter(|(_, score)| **score == best);
+    match (leaders.next(), leaders.next()) {
+        (Some((leader, _)), None) if best > 0 => Some(Some(*leader)),
+        _ => Some(None),
+    }
+}
+
+/// Every player for themselves, first to the kill limit wins
+#[derive(Default)]
+pub struct Deathmatch {
+    kills: HashMap<u32, u32>,
+}
+
+impl GameMode for Deathmatch {
+    fn kind(&self) -> ModeKind {
+        ModeKind::Deathmatch
+    }
+
+    fn on_round_start(&mut self, _area: &mut GameArea) {
+        self.kills.clear();
+    }
+
+    fn on_player_leave(&mut self, _area: &mut GameArea, object_id: u32) {
+        self.kills.remove(&object_id);
+    }
+
+    fn on_kill(&mut self, area: &mut GameArea, victim: u32, killer: u32) {
+        if killer != victim && area.is_player(killer) && area.is_player(victim) {
+            *self.kills.entry(killer).or_insert(0) += 1;
+        }
+    }
+
+    fn check_victory(&self, area: &GameArea) -> Option<Outcome> {
+        let kill_limit = area.config.mode.kill_limit;
+        if let Some((winner, _)) = self.kills.iter().find(|(_, kills)| **kills >= kill_limit) {
+            return Some(Outcome::Winner(*winner));
+        }
+
+        time_up_leader(area, &self.kills).map(|leader| match leader {
+            Some(winner) => Outcome::Winner(winner),
+            None => Outcome::Draw,
+        })
+    }
+}
+
+/// Teams pooling their kills toward the kill limit
+#[derive(Default)]
+pub struct TeamDeathmatch {
+    kills: HashMap<u32, u32>,
+}
+
+impl GameMode for TeamDeathmatch {
+    fn kind(&self) -> ModeKind {
+        ModeKind::TeamDeathmatch
+    }
+
+    fn team_count(&self, config: &TeamConfig) -> u32 {
+        config.count.max(2)
+    }
+
+    fn on_round_start(&mut self, _area: &mut GameArea) {
+        self.kills.clear();
+    }
+
+    fn on_kill(&mut self, area: &mut GameArea, victim: u32, killer: u32) {
+        match (area.team_of(killer), area.team_of(victim)) {
+            (Some(killer_team), Some(victim_team)) if killer_team != victim_team => {
+                *self.kills.entry(killer_team.0).or_insert(0) += 1;
+            }
+            _ => {}
+        }
+    }
+
+    fn check_victory(&self, area: &GameArea) -> Option<Outcome> {
+        let kill_limit = area.config.mode.kill_limit;
+        if let Some((team, _)) = self.kills.iter().find(|(_, kills)| **kills >= kill_limit) {
+            return Some(Outcome::WinningTeam(*team));
+        }
+
+        time_up_leader(area, &self.kills).map(|leader| match leader {
+            Some(team) => Outcome::WinningTeam(team),
+            None => Outcome::Draw,
+        })
+    }
+}
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EffectKind {
+    SpeedBoost,
+    Shield,
+    DamageMultiplier,
+    Slow,
+    Poison,
+}
+
+/// What happens when an effect is applied to something that already has it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stacking {
+    /// Restart the timer
+    Refresh,
+    /// Add another full duration, up to `max_stacks` of them
+    Extend,
+    /// Add a stack of magnitude, up to `max_stacks`, and restart the timer
+    Intensify,
+    /// Leave the running effect alone
+    Ignore,
+}
+
+/// Magnitude is a fraction for speed, shield and damage, and damage per second for poison
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EffectDefinition {
+    pub duration_ms: u64,
+    pub magnitude: f32,
+    pub stacking: Stacking,
+    pub max_stacks: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EffectConfig {
+    pub definitions: HashMap<EffectKind, EffectDefinition>,
+}
+
+impl Default for EffectConfig {
+    fn default() -> EffectConfig {
+        let definition = |duration_ms, magnitude, stacking, max_stacks| EffectDefinition {
+            duration_ms,
+            magnitude,
+            stacking,
+            max_stacks,
+        };
+
+        EffectConfig {
+            definitions: HashMap::from([
+                (
+                    EffectKind::SpeedBoost,
+                    definition(8000, 0.5, Stacking::Refresh, 1),
+                ),
+                (
+                    EffectKind::Shield,
+                    definition(10000, 0.5, Stacking::Refresh, 1),
+                ),
+                (
+                    EffectKind::DamageMultiplier,
+                    definition(8000, 1.0, Stacking::Extend, 3),
+                ),
+                (
+                    EffectKind::Slow,
+                    definition(3000, 0.4, Stacking::Refresh, 1),
+                ),
+                (
+                    EffectKind::Poison,
+                    definition(5000, 4.0, Stacking::Intensify, 3),
+                ),
+            ]),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: EffectKind,
+    pub magnitude: f32,
+    pub stacks: u32,
+    pub remaining_ms: u64,
+    #[serde(skip)]
+    pub source: Option<u32>,
+}
+
+/// Every effect currently running on a player or actor
+#[derive(Component, Clone, Debug, Default)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+    /// Poison damage built up since it was last dealt
+    pub pending_poison: f32,
+    /// Set when an effect runs out so the change can be broadcast
+    pub expired: bool,
+}
+
+impl StatusEffects {
+    pub fn get(&self, kind: EffectKind) -> Option<&StatusEffect> {
+        self.effects.iter().find(|effect| effect.kind == kind)
+    }
+
+    /// Combined magnitude of an effect, zero if it isn't running
+    pub fn magnitude(&self, kind: EffectKind) -> f32 {
+        self.get(kind)
+            .map_or(0.0, |effect| effect.magnitude * effect.stacks as f32)
+    }
+
+    pub fn speed_factor(&self) -> f32 {
+        let boost = 1.0 + self.magnitude(EffectKind::SpeedBoost);
+        let slow = 1.0 - self.magnitude(EffectKind::Slow).clamp(0.0, 1.0);
+        boost * slow
+    }
+
+    pub fn damage_factor(&self) -> f32 {
+        1.0 + self.magnitude(EffectKind::DamageMultiplier)
+    }
+
+    pub fn shield_factor(&self) -> f32 {
+        1.0 - self.magnitude(EffectKind::Shield).clamp(0.0, 1.0)
+    }
+
+    pub fn apply(&mut self, kind: EffectKind, definition: &EffectDefinition, source: Option<u32>) {
+        let max_stacks = definition.max_stacks.max(1);
+        let effect = match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => effect,
+            None => {
+                self.effects.push(StatusEffect {
+                    kind,
+                    magnitude: definition.magnitude,
+                    stacks: 1,
+                    remaining_ms: definition.duration_ms,
+                    source,
+                });
+                return;
+            }
+        };
+
+        match definition.stacking {
+            Stacking::Refresh => {
+                effect.remaining_ms = definition.duration_ms;
+            }
+            Stacking::Extend => {
+                let cap = definition.duration_ms * max_stacks as u64;
+                effect.remaining_ms = (effect.remaining_ms + definition.duration_ms).min(cap);
+            }
+            Stacking::Intensify => {
+                effect.stacks = (effect.stacks + 1).min(max_stacks);
+                effect.remaining_ms = definition.duration_ms;
+            }
+            Stacking::Ignore => return,
+        }
+        effect.source = source.or(effect.source);
+    }
+}
+
+/// Time since the last tick, for systems that count down in real time
+#[derive(Resource, Default)]
+pub struct TickDelta(pub Duration);
+
+/// Counts effects down, builds up poison damage and drops whatever has run out
+pub fn tick_status_effects(delta: Res<TickDelta>, mut query: Query<&mut StatusEffects>) {
+    let elapsed_ms = delta.0.as_millis() as u64;
+    for mut status in &mut query {
+        if status.effects.is_empty() {
+            continue;
+        }
+
+        status.pending_poison += status.magnitude(EffectKind::Poison) * delta.0.as_secs_f32();
+
+        for effect in status.effects.iter_mut() {
+            effect.remaining_ms = effect.remaining_ms.saturating_sub(elapsed_ms);
+        }
+
+        let before = status.effects.len();
+        status.effects.retain(|effect| effect.remaining_ms > 0);
+        if status.effects.len() != before {
+            status.expired = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(stacking: Stacking, max_stacks: u32) -> EffectDefinition {
+        EffectDefinition {
+            duration_ms: 1000,
+            magnitude: 2.0,
+            stacking,
+            max_stacks,
+        }
+    }
+
+    /// Applies an effect, lets `elapsed_ms` pass, then applies it again
+    fn reapply(definition: &EffectDefinition, elapsed_ms: u64) -> StatusEffects {
+        let mut status = StatusEffects::default();
+        status.apply(EffectKind::Poison, definition, Some(1));
+        status.effects[0].remaining_ms -= elapsed_ms;
+        status.apply(EffectKind::Poison, definition, Some(2));
+        status
+    }
+
+    #[test]
+    fn refresh_restarts_the_timer() {
+        let status = reapply(&definition(Stacking::Refresh, 3), 600);
+
+        let effect = status.get(EffectKind::Poison).unwrap();
+        assert_eq!(effect.remaining_ms, 1000);
+        assert_eq!(effect.stacks, 1);
+        assert_eq!(effect.source, Some(2));
+    }
+
+    #[test]
+    fn extend_adds_duration_up_to_the_cap() {
+        let status = reapply(&definition(Stacking::Extend, 3), 600);
+        assert_eq!(status.get(EffectKind::Poison).unwrap().remaining_ms, 1400);
+
+        let mut status = reapply(&definition(Stacking::Extend, 2), 0);
+        status.apply(EffectKind::Poison, &definition(Stacking::Extend, 2), None);
+        assert_eq!(status.get(EffectKind::Poison).unwrap().remaining_ms, 2000);
+    }
+
+    #[test]
+    fn intensify_adds_stacks_up_to_the_limit() {
+        let definition = definition(Stacking::Intensify, 2);
+        let mut status = reapply(&definition, 600);
+        assert_eq!(status.magnitude(EffectKind::Poison), 4.0);
+        assert_eq!(status.get(EffectKind::Poison).unwrap().remaining_ms, 1000);
+
+        status.apply(EffectKind::Poison, &definition, None);
+        assert_eq!(status.get(EffectKind::Poison).unwrap().stacks, 2);
+        assert_eq!(status.get(EffectKind::Poison).unwrap().source, Some(2));
+    }
+
+    #[test]
+    fn ignore_leaves_the_running_effect_alone() {
+        let status = reapply(&definition(Stacking::Ignore, 3), 600);
+
+        let effect = status.get(EffectKind::Poison).unwrap();
+        assert_eq!(effect.remaining_ms, 400);
+        assert_eq!(effect.source, Some(1));
+    }
+}
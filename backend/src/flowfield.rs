@@ -0,0 +1,282 @@
+use std::collections::{BinaryHeap, HashMap};
+
+use nalgebra::Vector3;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::{Duration, Instant};
+
+use crate::pathfinding::{Cell, Node, Pathfinder};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlowFieldConfig {
+    /// Cells expanded across all fields per tick
+    pub budget_per_tick: usize,
+    /// Rebuild a field this often even if its target hasn't changed cell
+    pub refresh_interval_ms: u64,
+}
+
+impl Default for FlowFieldConfig {
+    fn default() -> FlowFieldConfig {
+        FlowFieldConfig {
+            budget_per_tick: 4000,
+            refresh_interval_ms: 1000,
+        }
+    }
+}
+
+/// Cost-to-target of every grid cell, filled in by a Dijkstra search
+/// that can be paused and resumed
+struct FlowField {
+    target: Cell,
+    costs: Vec<f32>,
+    frontier: BinaryHeap<Node>,
+    started_at: Instant,
+}
+
+impl FlowField {
+    fn new(pathfinder: &Pathfinder, target: Cell) -> FlowField {
+        let mut costs = vec![f32::INFINITY; (pathfinder.width * pathfinder.width) as usize];
+        let mut frontier = BinaryHeap::new();
+
+        if let Some(idx) = pathfinder.index(target) {
+            costs[idx] = 0.0;
+            frontier.push(Node {
+                estimate: 0.0,
+                cell: target,
+            });
+        }
+
+        FlowField {
+            target,
+            costs,
+            frontier,
+            started_at: Instant::now(),
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.frontier.is_empty()
+    }
+
+    fn cost(&self, pathfinder: &Pathfinder, cell: Cell) -> f32 {
+        pathfinder
+            .index(cell)
+            .map(|idx| self.costs[idx])
+            .unwrap_or(f32::INFINITY)
+    }
+
+    /// Expands up to `budget` cells, returning how many were used
+    fn build(&mut self, pathfinder: &Pathfinder, budget: usize) -> usize {
+        let mut used = 0;
+        while used < budget {
+            let Node { estimate, cell } = match self.frontier.pop() {
+                Some(node) => node,
+                None => break,
+            };
+            used += 1;
+
+            if estimate > self.cost(pathfinder, cell) {
+                continue;
+            }
+
+            for neighbor in pathfinder.neighbors(cell) {
+                // the field points toward the target, so cost is of stepping back into `cell`
+                let step = match pathfinder.step_cost(neighbor, cell) {
+                    Some(step) => step,
+                    None => continue,
+                };
+
+                let cost = estimate + step;
+                let idx = pathfinder.index(neighbor).unwrap();
+                if cost < self.costs[idx] {
+                    self.costs[idx] = cost;
+                    self.frontier.push(Node {
+                        estimate: cost,
+                        cell: neighbor,
+                    });
+                }
+            }
+        }
+        used
+    }
+
+    fn direction(&self, pathfinder: &Pathfinder, position: &Vector3<f32>) -> Option<Vector3<f32>> {
+        let cell = pathfinder.cell(position);
+        if cell == self.target {
+            return None;
+        }
+
+        let best = pathfinder
+            .neighbors(cell)
+            .filter(|neighbor| pathfinder.step_cost(cell, *neighbor).is_some())
+            .map(|neighbor| (neighbor, self.cost(pathfinder, neighbor)))
+            .filter(|(_, cost)| cost.is_finite())
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))?;
+
+        let mut direction = pathfinder.center(best.0) - pathfinder.center(cell);
+        direction.y = 0.0;
+        Some(direction.normalize())
+    }
+}
+
+#[derive(Default)]
+struct FlowFieldSlot {
+    ready: Option<FlowField>,
+    building: Option<FlowField>,
+}
+
+/// Keeps a flow field toward each player, rebuilding them a slice at a time
+/// so hundreds of chasers can share one search instead of running A* each
+pub struct FlowFieldManager {
+    config: FlowFieldConfig,
+    fields: HashMap<u32, FlowFieldSlot>,
+}
+
+impl FlowFieldManager {
+    pub fn new(config: &FlowFieldConfig) -> FlowFieldManager {
+        FlowFieldManager {
+            config: config.clone(),
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Starts rebuilds for targets that moved and spends this tick's budget on them
+    pub fn update(&mut self, pathfinder: &Pathfinder, targets: &[(u32, Vector3<f32>)]) {
+        self.fields
+            .retain(|object_id, _| targets.iter().any(|(target_id, _)| target_id == object_id));
+
+        let refresh_interval = Duration::from_millis(self.config.refresh_interval_ms);
+        for (object_id, position) in targets {
+            let cell = pathfinder.cell(position);
+            let slot = self.fields.entry(*object_id).or_default();
+
+            // a field already being built finishes before the next one starts
+            let stale = match (&slot.building, &slot.ready) {
+                (Some(_), _) => false,
+                (None, Some(ready)) => {
+                    ready.target != cell || ready.started_at.elapsed() > refresh_interval
+                }
+                (None, None) => true,
+            };
+
+            if stale {
+                slot.building = Some(FlowField::new(pathfinder, cell));
+            }
+        }
+
+        let mut budget = self.config.budget_per_tick;
+        for slot in self.fields.values_mut() {
+            if budget == 0 {
+                break;
+            }
+
+            if let Some(building) = slot.building.as_mut() {
+                budget -= building.build(pathfinder, budget);
+                if building.is_complete() {
+                    slot.ready = slot.building.take();
+                }
+            }
+        }
+    }
+
+    /// Direction to head from `position` to reach the target, if its field is ready
+    pub fn sample(
+        &self,
+        pathfinder: &Pathfinder,
+        object_id: u32,
+        position: &Vector3<f32>,
+    ) -> Option<Vector3<f32>> {
+        self.fields
+            .get(&object_id)
+            .and_then(|slot| slot.ready.as_ref())
+            .and_then(|field| field.direction(pathfinder, position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pathfinding::PathfindingConfig;
+    use crate::terrain::{Terrain, TerrainType};
+
+    /// Flat grassland, with an ocean wall down x = 5 that is open at the far end when `walled`
+    fn pathfinder(walled: bool) -> Pathfinder {
+        let size = 10;
+        let mut terrain_map = vec![TerrainType::Grassland as u8; (size * size) as usize];
+        if walled {
+            for z in 0..size - 1 {
+                terrain_map[(5 * size + z) as usize] = TerrainType::Ocean as u8;
+            }
+        }
+        let terrain = Terrain {
+            size,
+            elevation_map: vec![0.0; (size * size) as usize],
+            terrain_map,
+            seed: 0,
+        };
+        let config = PathfindingConfig {
+            cell_size: 1,
+            ..PathfindingConfig::default()
+        };
+        Pathfinder::new(&terrain, &config)
+    }
+
+    fn manager(budget_per_tick: usize) -> FlowFieldManager {
+        FlowFieldManager::new(&FlowFieldConfig {
+            budget_per_tick,
+            refresh_interval_ms: 60000,
+        })
+    }
+
+    #[test]
+    fn fields_are_built_across_ticks_within_the_budget() {
+        let pathfinder = pathfinder(false);
+        let mut manager = manager(10);
+        let targets = [(1, Vector3::new(8.5, 0.0, 1.5))];
+        let position = Vector3::new(1.5, 0.0, 1.5);
+
+        manager.update(&pathfinder, &targets);
+        assert!(manager.sample(&pathfinder, 1, &position).is_none());
+
+        let mut ticks = 1;
+        while manager.sample(&pathfinder, 1, &position).is_none() {
+            manager.update(&pathfinder, &targets);
+            ticks += 1;
+            assert!(ticks < 100, "field never finished");
+        }
+        assert!(ticks > 2);
+
+        let direction = manager.sample(&pathfinder, 1, &position).unwrap();
+        assert!(direction.x > 0.9);
+        assert_eq!(direction.z, 0.0);
+    }
+
+    #[test]
+    fn fields_lead_around_water() {
+        let pathfinder = pathfinder(true);
+        let mut manager = manager(10000);
+        manager.update(&pathfinder, &[(1, Vector3::new(8.5, 0.0, 1.5))]);
+
+        let direction = manager
+            .sample(&pathfinder, 1, &Vector3::new(4.5, 0.0, 1.5))
+            .unwrap();
+        assert!(direction.z > 0.0);
+        assert!(direction.x <= 0.0);
+    }
+
+    #[test]
+    fn fields_are_dropped_with_their_target() {
+        let pathfinder = pathfinder(false);
+        let mut manager = manager(10000);
+        let position = Vector3::new(1.5, 0.0, 1.5);
+
+        manager.update(&pathfinder, &[(1, Vector3::new(8.5, 0.0, 1.5))]);
+        assert!(manager.sample(&pathfinder, 1, &position).is_some());
+
+        manager.update(&pathfinder, &[]);
+        assert!(manager.sample(&pathfinder, 1, &position).is_none());
+    }
+}
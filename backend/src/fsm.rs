@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
+use nalgebra::Vector3;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::{Duration, Instant};
+
+use crate::actor::ActorType;
+use crate::behavior::{ActorBehavior, Chase, Flee, Perception};
+use crate::game::FrozenGameObject;
+use crate::pathfinding::Path;
+
+const GUARD_DEFINITION: &str = include_str!("../behaviors/guard.json");
+
+const PATROL_RADIUS: f32 = 30.0;
+const WAYPOINT_REACHED: f32 = 5.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum State {
+    Idle,
+    Patrol,
+    Chase,
+    Attack,
+    Flee,
+    Dead,
+}
+
+/// Predicates over a perception that gate a transition
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Condition {
+    Always,
+    PlayerWithin(f32),
+    NoPlayerWithin(f32),
+    HealthBelow(u8),
+    HealthAtLeast(u8),
+    AlliesAtLeast(usize),
+    AlliesBelow(usize),
+    TimeInState(u64),
+    All(Vec<Condition>),
+    Any(Vec<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    fn evaluate(&self, perception: &Perception, time_in_state: Duration) -> bool {
+        match self {
+            Condition::Always => true,
+            Condition::PlayerWithin(range) => nearest_player_distance(perception)
+                .map(|distance| distance <= *range)
+                .unwrap_or(false),
+            Condition::NoPlayerWithin(range) => nearest_player_distance(perception)
+                .map(|distance| distance > *range)
+                .unwrap_or(true),
+            Condition::HealthBelow(health) => perception.object.object.health < *health,
+            Condition::HealthAtLeast(health) => perception.object.object.health >= *health,
+            Condition::AlliesAtLeast(count) => perception.actors.len() >= *count,
+            Condition::AlliesBelow(count) => perception.actors.len() < *count,
+            Condition::TimeInState(millis) => time_in_state >= Duration::from_millis(*millis),
+            Condition::All(conditions) => conditions
+                .iter()
+                .all(|condition| condition.evaluate(perception, time_in_state)),
+            Condition::Any(conditions) => conditions
+                .iter()
+                .any(|condition| condition.evaluate(perception, time_in_state)),
+            Condition::Not(condition) => !condition.evaluate(perception, time_in_state),
+        }
+    }
+}
+
+fn nearest_player_distance(perception: &Perception) -> Option<f32> {
+    perception
+        .nearest_player()
+        .map(|player| player.position.metric_distance(&perception.object.position))
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Transition {
+    pub when: Condition,
+    pub to: State,
+}
+
+/// Designer-authored state machine: per-state transitions checked in order,
+/// with `any` transitions checked first from every state. `Dead` is final.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FsmDefinition {
+    pub initial: State,
+    #[serde(default)]
+    pub any: Vec<Transition>,
+    #[serde(default)]
+    pub states: HashMap<State, Vec<Transition>>,
+}
+
+impl FsmDefinition {
+    pub fn from_json(json: &str) -> Result<FsmDefinition, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// The guard compiled into the binary, used when no definition is loaded for it
+    pub fn guard() -> Arc<FsmDefinition> {
+        static GUARD: OnceLock<Arc<FsmDefinition>> = OnceLock::new();
+        GUARD
+            .get_or_init(|| {
+                Arc::new(
+                    FsmDefinition::from_json(GUARD_DEFINITION)
+                        .expect("invalid built-in guard behavior"),
+                )
+            })
+            .clone()
+    }
+
+    fn next_state(
+        &self,
+        state: State,
+        perception: &Perception,
+        time_in_state: Duration,
+    ) -> Option<State> {
+        if state == State::Dead {
+            return None;
+        }
+
+        let transitions = self.states.get(&state).into_iter().flatten();
+        self.any
+            .iter()
+            .chain(transitions)
+            .find(|transition| {
+                transition.to != state && transition.when.evaluate(perception, time_in_state)
+            })
+            .map(|transition| transition.to)
+    }
+}
+
+/// Where state machine definitions are loaded from and which actor types run them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BehaviorConfig {
+    /// Every `<name>.json` in here is loaded as the definition `<name>`
+    pub dir: PathBuf,
+    /// Definition each actor type runs in place of its built-in behavior
+    pub actors: HashMap<ActorType, String>,
+}
+
+impl Default for BehaviorConfig {
+    fn default() -> BehaviorConfig {
+        BehaviorConfig {
+            dir: PathBuf::from("behaviors"),
+            actors: HashMap::from([(ActorType::Guard, "guard".to_string())]),
+        }
+    }
+}
+
+/// Definitions loaded from the behaviors directory, looked up per actor type
+#[derive(Debug, Default)]
+pub struct FsmDefinitions {
+    definitions: HashMap<String, Arc<FsmDefinition>>,
+    actors: HashMap<ActorType, String>,
+}
+
+impl FsmDefinitions {
+    pub fn new(config: &BehaviorConfig) -> FsmDefinitions {
+        FsmDefinitions {
+            definitions: load_definitions(&config.dir),
+            actors: config.actors.clone(),
+        }
+    }
+
+    pub fn for_actor(&self, actor_type: &ActorType) -> Option<Arc<FsmDefinition>> {
+        let name = self.actors.get(actor_type)?;
+        let definition = self.definitions.get(name);
+        if definition.is_none() {
+            tracing::warn!("no behavior named {} for {:?}", name, actor_type);
+        }
+        definition.cloned()
+    }
+}
+
+/// Reads every definition in `dir`, logging and skipping any that don't parse
+fn load_definitions(dir: &std::path::Path) -> HashMap<String, Arc<FsmDefinition>> {
+    let mut definitions = HashMap::new();
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!("could not read behaviors from {:?}: {}", dir, e);
+            return definitions;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+            continue;
+        }
+        let name = match path.file_stem().and_then(|stem| stem.to_str()) {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        let definition = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| FsmDefinition::from_json(&json).map_err(|e| e.to_string()));
+        match definition {
+            Ok(definition) => {
+                tracing::info!("loaded behavior {} from {:?}", name, path);
+                definitions.insert(name, Arc::new(definition));
+            }
+            Err(e) => tracing::error!("ignoring broken behavior {:?}: {}", path, e),
+        }
+    }
+    definitions
+}
+
+/// Runs an `FsmDefinition`, mapping each state onto a steering behavior
+pub struct FsmBehavior {
+    definition: Arc<FsmDefinition>,
+    state: State,
+    entered_at: Instant,
+    home: Option<Vector3<f32>>,
+    waypoint: usize,
+    chase: Chase,
+    flee: Flee,
+}
+
+impl FsmBehavior {
+    pub fn new(definition: Arc<FsmDefinition>) -> FsmBehavior {
+        FsmBehavior {
+            state: definition.initial,
+            definition,
+            entered_at: Instant::now(),
+            home: None,
+            waypoint: 0,
+            chase: Chase::default(),
+            flee: Flee::default(),
+        }
+    }
+
+    fn patrol(&mut self, actor: &FrozenGameObject) -> Vector3<f32> {
+        let home = *self.home.get_or_insert(actor.position);
+
+        let corners = [(1.0, 1.0), (1.0, -1.0), (-1.0, -1.0), (-1.0, 1.0)];
+        let (dx, dz) = corners[self.waypoint % corners.len()];
+        let waypoint = home + Vector3::new(dx * PATROL_RADIUS, 0.0, dz * PATROL_RADIUS);
+
+        let mut offset = waypoint - actor.position;
+        offset.y = 0.0;
+        if offset.magnitude() < WAYPOINT_REACHED {
+            self.waypoint += 1;
+        }
+
+        (offset - actor.velocity) / 25.0
+    }
+
+    fn attack(&self, perception: &Perception) -> Vector3<f32> {
+        let actor = &perception.object;
+        match perception.nearest_player() {
+            Some(target) => (target.position - actor.position - actor.velocity) / 10.0,
+            None => Vector3::new(0.0, 0.0, 0.0),
+        }
+    }
+}
+
+impl ActorBehavior for FsmBehavior {
+    fn perceive(&mut self, perception: &Perception) {
+        let time_in_state = self.entered_at.elapsed();
+        if let Some(next) = self
+            .definition
+            .next_state(self.state, perception, time_in_state)
+        {
+            tracing::debug!(
+                "actor {} {:?} -> {:?}",
+                perception.actor.actor_id,
+                self.state,
+                next
+            );
+            self.state = next;
+            self.entered_at = Instant::now();
+        }
+    }
+
+    fn path_request(&mut self, perception: &Perception) -> Option<Vector3<f32>> {
+        match self.state {
+            State::Chase => self.chase.path_request(perception),
+            _ => None,
+        }
+    }
+
+    fn receive_path(&mut self, path: Option<Path>) {
+        self.chase.receive_path(path);
+    }
+
+    fn decide(&mut self, perception: &Perception) -> Option<Vector3<f32>> {
+        match self.state {
+            State::Idle | State::Dead => None,
+            State::Patrol => Some(self.patrol(&perception.object)),
+            State::Chase => self.chase.decide(perception),
+            State::Attack => Some(self.attack(perception)),
+            State::Flee => self.flee.decide(perception),
+        }
+    }
+}
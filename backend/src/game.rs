@@ -4,6 +4,7 @@ use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::ops::AddAssign;
 use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Arc;
 
 use nalgebra::Vector3;
 
@@ -14,27 +15,51 @@ use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
 use tokio::time::{Duration, Instant};
 
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use fps_counter::FPSCounter;
 
+use tracing::Instrument;
+
 use bevy_ecs::prelude::*;
 
 use crate::actor::{actor_main, Actor, ActorType};
+use crate::admin::{AdminCommand, AdminReply, AreaStats, PlayerInfo};
+use crate::api::{PlayerSummary, ServerStatus, WorldInfo};
+use crate::behavior::Perception;
+use crate::chat::{Chat, ChatChannel, ChatMessage};
+use crate::combat::{Combat, Damage, DamageKind, Hit};
+use crate::config::Config;
+use crate::effects::{tick_status_effects, EffectKind, StatusEffect, StatusEffects, TickDelta};
+use crate::flowfield::FlowFieldManager;
+use crate::fsm::FsmDefinitions;
+use crate::items::{Inventory, Item, ItemKind};
+use crate::metrics::METRICS;
+use crate::mode::{new_mode, GameMode, Outcome, Round, RoundState};
+use crate::moderation::{validate_username, BanList, HelloError};
 use crate::net::StateUpdate;
+use crate::pathfinding::{Path, Pathfinder};
+use crate::scoreboard::{LeaderboardEntry, Scoreboard, ScoreboardEntry};
+use crate::snapshot::{save_snapshot, SavedActor, SavedObject, Snapshot, SNAPSHOT_VERSION};
+use crate::spawner::Spawner;
+use crate::spectator::Spectator;
+use crate::teams::{largest_team, smallest_team, team_sizes, Team};
 use crate::terrain::{Terrain, TerrainType};
 
 use crate::data_structs::BinLattice;
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ObjectType {
     Actor,
     Item,
     Player,
+    Projectile,
 }
 
 static GAME_OBJECT_COUNTER: AtomicI32 = AtomicI32::new(1);
 
+const MAX_ACTOR_SPEED: f32 = 1.0;
+
 #[derive(Debug, Copy, Clone)]
 pub struct Client {
     pub client_id: u32,
@@ -47,50 +72,146 @@ pub struct Player {
     pub conn: UnboundedSender<GameResponse>,
     pub username: String,
     pub object_id: u32,
+    pub last_fired: Option<Instant>,
+    pub death: Option<Death>,
+}
+
+/// Who killed a player and when, kept until they respawn
+#[derive(Debug, Copy, Clone)]
+pub struct Death {
+    pub killer: Option<u32>,
+    pub died_at: Instant,
+}
+
+/// Why a player who asked to respawn was kept waiting
+#[derive(Clone, Debug, Serialize)]
+pub enum RespawnError {
+    NotDead,
+    RoundInProgress,
+    TooSoon(u64),
+    NoSpawnPoint,
 }
 
 #[derive(Debug)]
 pub enum GameMessage {
     // Client Messages
     Hello(Client, UnboundedSender<GameResponse>, String),
+    Spectate(Client, UnboundedSender<GameResponse>, bool),
+    Follow(Client, Option<u32>),
     Goodbye(Client),
     Ping(Client, u64),
     Move(Client, f32, f32, f32),
+    Fire(Client, f32, f32, f32),
+    RequestRespawn(Client),
+    Chat(Client, ChatChannel, String),
 
     // Game Messages
     Tick(Instant),
 
     // Actor Messages
-    Die(u32),
-    Respawn(u32),
-    Scan(
-        u32,
-        oneshot::Sender<(
-            Actor,
-            FrozenGameObject,
-            Vec<FrozenGameObject>,
-            Vec<FrozenGameObject>,
-        )>,
-    ),
+    Scan(u32, oneshot::Sender<Perception>),
     ActorMove(u32, f32, f32, f32),
+    FindPath(u32, Vector3<f32>, oneshot::Sender<Option<Path>>),
+
+    // Admin Messages
+    ReloadConfig(Box<Config>),
+    Admin(AdminCommand, oneshot::Sender<AdminReply>),
+    Shutdown(String, oneshot::Sender<()>),
+
+    // Api Messages
+    Status(oneshot::Sender<ServerStatus>),
+    Players(oneshot::Sender<Vec<PlayerSummary>>),
+    World(oneshot::Sender<WorldInfo>),
+    TerrainMap(oneshot::Sender<(u32, Vec<u8>)>),
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize)]
 pub enum GameResponse {
     Error(u32, String),
     StateUpdate(StateUpdate),
     Pong(u64),
-    Goodbye(),
+    Goodbye(Option<String>),
     Notice(String),
     ElevationMap(u32, u32, Vec<f32>),
     TerrainMap(u32, u32, Vec<u8>),
+    Damaged(u32, u32, u8, u8),
+    Killed(u32, u32),
+    Died(Option<u32>, u64),
+    Respawned(u32),
+    Inventory(Inventory),
+    RoundStarted(u32),
+    WaveStarted(u32, u32),
+    RoundEnded(u32, Outcome),
+    RoundStateChanged(RoundState, u64),
+    ChatMessage(ChatMessage),
+    Scoreboard(Vec<ScoreboardEntry>),
+    Leaderboard(Vec<LeaderboardEntry>),
+    HelloRejected(HelloError),
+    RespawnRejected(RespawnError),
+}
+
+impl GameMessage {
+    /// Variant name, used to label spans
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMessage::Hello(..) => "Hello",
+            GameMessage::Spectate(..) => "Spectate",
+            GameMessage::Follow(..) => "Follow",
+            GameMessage::Goodbye(..) => "Goodbye",
+            GameMessage::Ping(..) => "Ping",
+            GameMessage::Move(..) => "Move",
+            GameMessage::Fire(..) => "Fire",
+            GameMessage::RequestRespawn(..) => "RequestRespawn",
+            GameMessage::Chat(..) => "Chat",
+            GameMessage::Tick(..) => "Tick",
+            GameMessage::Scan(..) => "Scan",
+            GameMessage::ActorMove(..) => "ActorMove",
+            GameMessage::FindPath(..) => "FindPath",
+            GameMessage::ReloadConfig(..) => "ReloadConfig",
+            GameMessage::Admin(..) => "Admin",
+            GameMessage::Shutdown(..) => "Shutdown",
+            GameMessage::Status(..) => "Status",
+            GameMessage::Players(..) => "Players",
+            GameMessage::World(..) => "World",
+            GameMessage::TerrainMap(..) => "TerrainMap",
+        }
+    }
+}
+
+impl GameResponse {
+    /// Variant name, used to label metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameResponse::Error(..) => "Error",
+            GameResponse::StateUpdate(..) => "StateUpdate",
+            GameResponse::Pong(..) => "Pong",
+            GameResponse::Goodbye(..) => "Goodbye",
+            GameResponse::Notice(..) => "Notice",
+            GameResponse::ElevationMap(..) => "ElevationMap",
+            GameResponse::TerrainMap(..) => "TerrainMap",
+            GameResponse::Damaged(..) => "Damaged",
+            GameResponse::Killed(..) => "Killed",
+            GameResponse::Died(..) => "Died",
+            GameResponse::Respawned(..) => "Respawned",
+            GameResponse::Inventory(..) => "Inventory",
+            GameResponse::RoundStarted(..) => "RoundStarted",
+            GameResponse::WaveStarted(..) => "WaveStarted",
+            GameResponse::RoundEnded(..) => "RoundEnded",
+            GameResponse::RoundStateChanged(..) => "RoundStateChanged",
+            GameResponse::ChatMessage(..) => "ChatMessage",
+            GameResponse::Scoreboard(..) => "Scoreboard",
+            GameResponse::Leaderboard(..) => "Leaderboard",
+            GameResponse::HelloRejected(..) => "HelloRejected",
+            GameResponse::RespawnRejected(..) => "RespawnRejected",
+        }
+    }
 }
 
 impl Player {
     pub fn send(&self, response: GameResponse) {
         let result = self.conn.send(response);
         if let Err(e) = result {
-            log::error!("game response write error {:?}: {}", self.client, e);
+            tracing::error!("game response write error {:?}: {}", self.client, e);
         }
     }
 }
@@ -113,6 +234,18 @@ struct Acceleration {
 #[derive(Component)]
 struct Alive;
 
+#[derive(Component, Debug, Copy, Clone)]
+struct Projectile {
+    owner: u32,
+    damage: u8,
+    ticks_left: u32,
+}
+
+#[derive(Component, Debug, Copy, Clone)]
+struct Corpse {
+    died_at: Instant,
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct GameObject {
     pub alive: bool,
@@ -129,6 +262,9 @@ pub struct FrozenGameObject {
     pub position: Vector3<f32>,
     pub velocity: Vector3<f32>,
     pub acceleration: Vector3<f32>,
+    pub item: Option<String>,
+    pub effects: Vec<StatusEffect>,
+    pub team: Option<u32>,
 }
 
 impl GameObject {
@@ -145,37 +281,115 @@ impl GameObject {
 }
 
 pub struct GameArea {
+    pub config: Config,
     pub world: World,
     pub schedule: Schedule,
     pub terrain: Terrain,
+    pub pathfinder: Arc<Pathfinder>,
+    pub flow_fields: FlowFieldManager,
+    pub behaviors: FsmDefinitions,
     pub entities: HashMap<Entity, u32>, // maps entity id to object id
     pub objects: HashMap<u32, GameObject>,
     pub actors: HashMap<u32, Actor>,
     pub actor_handles: HashMap<u32, JoinHandle<()>>,
+    pub spawner: Spawner,
+    pub combat: Combat,
+    pub scoreboard: Scoreboard,
+    pub chat: Chat,
+    pub mode: Option<Box<dyn GameMode>>, // taken out while its hooks run
+    /// Kills waiting for the mode to score them, as `(victim, killer)`
+    pending_kills: Vec<(u32, u32)>,
+    pub round: Round,
     pub players: HashMap<u32, Player>,
+    pub spectators: HashMap<u32, Spectator>,
+    pub bans: BanList,
     pub game_tx: UnboundedSender<GameMessage>,
     pub actor_index: BinLattice,
     pub ticks: u32,
     pub last_tick: Instant,
     pub fps_counter: FPSCounter,
+    pub tps: usize,
+    pub paused: bool,
+    pub started_at: Instant,
+    pub shutting_down: bool,
+    pub last_snapshot: Instant,
 }
 
 impl GameArea {
-    pub fn new(area_size: u32, game_tx: UnboundedSender<GameMessage>) -> GameArea {
+    pub fn new(area_size: u32, config: Config, game_tx: UnboundedSender<GameMessage>) -> GameArea {
+        GameArea::with_terrain(Terrain::new(area_size), config, game_tx)
+    }
+
+    /// Rebuilds a saved world, restarting its actors
+    pub fn restore(
+        snapshot: Snapshot,
+        config: Config,
+        game_tx: UnboundedSender<GameMessage>,
+    ) -> GameArea {
+        let terrain = Terrain::with_seed(snapshot.size, snapshot.seed);
+        let mut area = GameArea::with_terrain(terrain, config, game_tx);
+        area.ticks = snapshot.ticks;
+        area.round.number = snapshot.round;
+
+        let count = snapshot.objects.len();
+        for saved in snapshot.objects {
+            area.restore_object(saved);
+        }
+        tracing::info!(
+            objects = count,
+            actors = area.actors.len(),
+            "restored world from snapshot"
+        );
+        area
+    }
+
+    fn with_terrain(
+        terrain: Terrain,
+        config: Config,
+        game_tx: UnboundedSender<GameMessage>,
+    ) -> GameArea {
+        let pathfinder = Arc::new(Pathfinder::new(&terrain, &config.pathfinding));
+        let flow_fields = FlowFieldManager::new(&config.flow_fields);
+        let behaviors = FsmDefinitions::new(&config.behaviors);
+        let spawner = Spawner::new(&config.spawner);
+        let combat = Combat::new(&config.combat);
+        let scoreboard = Scoreboard::new(&config.scoreboard);
+        let chat = Chat::new(&config.chat);
+        let bans = BanList::new(&config.moderation);
+        let mode = new_mode(config.mode.mode);
+
         let mut area = GameArea {
+            config,
             world: World::new(),
             schedule: Schedule::default(),
-            terrain: Terrain::new(area_size),
+            terrain,
+            pathfinder,
+            flow_fields,
+            behaviors,
             entities: HashMap::new(),
             objects: HashMap::new(),
             actors: HashMap::new(),
             players: HashMap::new(),
+            spectators: HashMap::new(),
+            bans,
             actor_handles: HashMap::new(),
+            spawner,
+            combat,
+            scoreboard,
+            chat,
+            mode: Some(mode),
+            pending_kills: Vec::new(),
+            round: Round::new(),
             game_tx,
             actor_index: BinLattice::new(50),
             ticks: 0,
             last_tick: Instant::now(),
             fps_counter: FPSCounter::default(),
+            tps: 0,
+            paused: false,
+            started_at: Instant::now(),
+            shutting_down: false,
+            last_snapshot: Instant::now(),
         };
 
         area.schedule.add_systems(
@@ -187,6 +401,16 @@ impl GameArea {
             },
         );
 
+        area.world.insert_resource(TickDelta::default());
+        area.schedule.add_systems(tick_status_effects);
+
+        area.schedule
+            .add_systems(|mut query: Query<&mut Projectile>| {
+                for mut projectile in &mut query {
+                    projectile.ticks_left = projectile.ticks_left.saturating_sub(1);
+                }
+            });
+
         area
     }
 
@@ -204,12 +428,187 @@ impl GameArea {
             position: pos.value.clone(),
             velocity: vel.value.clone(),
             acceleration: accel.value.clone(),
+            item: self
+                .world
+                .get::<Item>(object.entity)
+                .map(|item| item.name.clone()),
+            effects: self
+                .world
+                .get::<StatusEffects>(object.entity)
+                .map(|status| status.effects.clone())
+                .unwrap_or_default(),
+            team: self.world.get::<Team>(object.entity).map(|team| team.0),
+        }
+    }
+
+    pub fn team_of(&self, object_id: u32) -> Option<Team> {
+        self.objects
+            .get(&object_id)
+            .and_then(|obj| self.world.get::<Team>(obj.entity))
+            .cloned()
+    }
+
+    fn team_count(&self) -> u32 {
+        self.mode
+            .as_ref()
+            .map_or(0, |mode| mode.team_count(&self.config.teams))
+    }
+
+    fn player_teams(&self) -> HashMap<Team, usize> {
+        let teams = self
+            .players
+            .values()
+            .flat_map(|player| self.team_of(player.object_id));
+        team_sizes(self.team_count(), teams)
+    }
+
+    fn set_team(&mut self, object_id: u32, team: Option<Team>) {
+        let entity = match self.objects.get(&object_id) {
+            Some(obj) => obj.entity,
+            None => return,
+        };
+
+        let mut entity = self.world.entity_mut(entity);
+        match team {
+            Some(team) => {
+                entity.insert(team);
+            }
+            None => {
+                entity.remove::<Team>();
+            }
+        }
+        self.broadcast_object(object_id);
+    }
+
+    /// Puts a player on whichever team is short, or none if the mode has no teams
+    fn assign_team(&mut self, object_id: u32) {
+        self.set_team(object_id, None);
+        let team = smallest_team(&self.player_teams());
+        self.set_team(object_id, team);
+    }
+
+    /// Moves players off the biggest team until no team is too far ahead,
+    /// preferring players who are dead and won't notice
+    fn rebalance_teams(&mut self) {
+        loop {
+            let sizes = self.player_teams();
+            let (largest, smallest) = match (largest_team(&sizes), smallest_team(&sizes)) {
+                (Some(largest), Some(smallest)) => (largest, smallest),
+                _ => return,
+            };
+            if sizes[&largest] - sizes[&smallest] < self.config.teams.max_imbalance.max(2) {
+                return;
+            }
+
+            let mut candidates: Vec<&Player> = self
+                .players
+                .values()
+                .filter(|player| self.team_of(player.object_id) == Some(largest))
+                .collect();
+            candidates.sort_by_key(|player| player.death.is_none());
+            let (client_id, object_id) = match candidates.first() {
+                Some(player) => (player.client.client_id, player.object_id),
+                None => return,
+            };
+
+            self.set_team(object_id, Some(smallest));
+            if let Some(player) = self.players.get(&client_id) {
+                let notice = format!("Moved to team {} to balance the teams", smallest.0);
+                player.send(GameResponse::Notice(notice));
+            }
+        }
+    }
+
+    /// Whether the mode's teams protect `target` from `source`
+    fn is_friendly(&self, source: u32, target: u32) -> bool {
+        if source == target || self.config.teams.friendly_fire {
+            return false;
         }
+        match (self.team_of(source), self.team_of(target)) {
+            (Some(source_team), Some(target_team)) => source_team == target_team,
+            _ => false,
+        }
+    }
+
+    fn status_effects(&self, object_id: u32) -> Option<&StatusEffects> {
+        self.objects
+            .get(&object_id)
+            .and_then(|obj| self.world.get::<StatusEffects>(obj.entity))
+    }
+
+    fn speed_factor(&self, object_id: u32) -> f32 {
+        self.status_effects(object_id)
+            .map_or(1.0, |status| status.speed_factor())
+    }
+
+    /// Starts or stacks an effect on an object and lets everyone know
+    pub fn apply_effect(&mut self, object_id: u32, kind: EffectKind, source: Option<u32>) {
+        let definition = match self.config.effects.definitions.get(&kind) {
+            Some(definition) => definition.clone(),
+            None => return,
+        };
+        let entity = match self.objects.get(&object_id).filter(|obj| obj.alive) {
+            Some(obj) => obj.entity,
+            None => return,
+        };
+
+        let mut entity = self.world.entity_mut(entity);
+        match entity.get_mut::<StatusEffects>() {
+            Some(mut status) => status.apply(kind, &definition, source),
+            None => {
+                let mut status = StatusEffects::default();
+                status.apply(kind, &definition, source);
+                entity.insert(status);
+            }
+        }
+
+        self.broadcast_object(object_id);
+    }
+
+    pub fn has_username(&self, username: &str) -> bool {
+        self.players
+            .values()
+            .any(|player| player.username.eq_ignore_ascii_case(username))
     }
 
-    pub fn has_username(&self, username: &String) -> bool {
-        let usernames: Vec<String> = self.players.values().map(|x| x.username.clone()).collect();
-        usernames.iter().any(|x| x.eq(username))
+    /// Whether a connection may join, as a player if it gave a username or else as a spectator
+    fn check_hello(&self, client: &Client, username: Option<&str>) -> Result<(), HelloError> {
+        if self.players.contains_key(&client.client_id)
+            || self.spectators.contains_key(&client.client_id)
+        {
+            return Err(HelloError::AlreadyConnected);
+        }
+        if self.bans.is_banned(username, client.addr.ip()) {
+            return Err(HelloError::Banned);
+        }
+
+        match username {
+            Some(username) => {
+                validate_username(&self.config.moderation, username)
+                    .map_err(HelloError::InvalidUsername)?;
+                if self.has_username(username) {
+                    return Err(HelloError::UsernameTaken);
+                }
+            }
+            None => {
+                if self.spectators.len() >= self.config.spectators.max_spectators {
+                    return Err(HelloError::TooManySpectators);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn reject_hello(
+        client: Client,
+        client_conn: &UnboundedSender<GameResponse>,
+        error: HelloError,
+    ) {
+        tracing::info!("rejected hello from {:?}: {:?}", client, error);
+        let result = client_conn.send(GameResponse::HelloRejected(error));
+        if let Err(e) = result {
+            tracing::error!("game response write error {:?}: {}", client, e);
+        }
     }
 
     pub fn add_object(
@@ -227,10 +626,13 @@ impl GameArea {
         self.objects.get_mut(&key).unwrap()
     }
 
-    pub fn add_item(&mut self, x: f32, y: f32, z: f32) -> &mut GameObject {
+    pub fn add_item(&mut self, name: &str, x: f32, y: f32, z: f32) -> &mut GameObject {
         let entity = self
             .world
             .spawn((
+                Item {
+                    name: name.to_string(),
+                },
                 Position {
                     value: Vector3::new(x, y, z),
                 },
@@ -269,10 +671,12 @@ impl GameArea {
     }
 
     pub fn add_player(&mut self, x: f32, y: f32, z: f32) -> &mut GameObject {
+        let inventory = Inventory::new(&self.config.items.starting_inventory);
         let entity = self
             .world
             .spawn((
                 Alive,
+                inventory,
                 Position {
                     value: Vector3::new(x, y, z),
                 },
@@ -289,19 +693,111 @@ impl GameArea {
         obj
     }
 
-    pub fn spawn_actor(&mut self, actor_type: ActorType) -> u32 {
-        let tx = self.game_tx.clone();
-        let mut rng = rand::thread_rng();
-
-        let size = self.terrain.size as f32;
-        let x = rng.gen::<f32>() * size;
-        let z = rng.gen::<f32>() * size;
+    pub fn add_projectile(
+        &mut self,
+        owner: u32,
+        position: Vector3<f32>,
+        velocity: Vector3<f32>,
+    ) -> &mut GameObject {
+        let projectile_config = &self.config.projectiles;
+        let entity = self
+            .world
+            .spawn((
+                Alive,
+                Position { value: position },
+                Velocity { value: velocity },
+                Acceleration {
+                    value: Vector3::new(0.0, 0.0, 0.0),
+                },
+                Projectile {
+                    owner,
+                    damage: projectile_config.damage,
+                    ticks_left: projectile_config.lifetime_ticks,
+                },
+            ))
+            .id();
 
-        let elevation = self.terrain.get_elevation(
-            x.clamp(0.0, size - 1.0) as u32,
-            z.clamp(0.0, size - 1.0) as u32,
+        let obj = self.add_object(
+            ObjectType::Projectile,
+            entity,
+            position.x,
+            position.y,
+            position.z,
         );
-        let y = elevation;
+        obj
+    }
+
+    /// Tells everyone an object is gone and drops it from the world
+    fn despawn_object(&mut self, object_id: u32) {
+        if let Some(obj) = self.objects.get_mut(&object_id) {
+            obj.alive = false;
+        }
+        self.broadcast_object(object_id);
+
+        if let Some(obj) = self.objects.remove(&object_id) {
+            self.entities.remove(&obj.entity);
+            self.world.despawn(obj.entity);
+        }
+        self.combat.forget(object_id);
+    }
+
+    pub fn is_player(&self, object_id: u32) -> bool {
+        self.players
+            .values()
+            .any(|player| player.object_id == object_id)
+    }
+
+    pub fn is_actor_alive(&self, actor_id: u32) -> bool {
+        self.actors
+            .get(&actor_id)
+            .and_then(|actor| self.objects.get(&actor.object_id))
+            .is_some_and(|actor_obj| actor_obj.alive)
+    }
+
+    pub fn living_player_positions(&self) -> Vec<Vector3<f32>> {
+        self.players
+            .values()
+            .flat_map(|player| self.objects.get(&player.object_id))
+            .filter(|player_obj| player_obj.alive)
+            .flat_map(|player_obj| self.world.get::<Position>(player_obj.entity))
+            .map(|position| position.value)
+            .collect()
+    }
+
+    fn player_positions(&self) -> Vec<Vector3<f32>> {
+        self.players
+            .values()
+            .flat_map(|player| self.objects.get(&player.object_id))
+            .flat_map(|player_obj| self.world.get::<Position>(player_obj.entity))
+            .map(|position| position.value)
+            .collect()
+    }
+
+    fn actor_positions(&self) -> Vec<Vector3<f32>> {
+        self.actors
+            .values()
+            .flat_map(|actor| self.objects.get(&actor.object_id))
+            .filter(|actor_obj| actor_obj.alive)
+            .flat_map(|actor_obj| self.world.get::<Position>(actor_obj.entity))
+            .map(|position| position.value)
+            .collect()
+    }
+
+    /// Spawns where the spawner finds room, or not at all if there is no valid point
+    pub fn spawn_actor(&mut self, actor_type: ActorType) -> Option<u32> {
+        let position = self.spawner.find_spawn_point(
+            &self.terrain,
+            &self.pathfinder,
+            &[],
+            &self.player_positions(),
+            &self.actor_positions(),
+        )?;
+
+        Some(self.spawn_actor_at(actor_type, position))
+    }
+
+    pub fn spawn_actor_at(&mut self, actor_type: ActorType, position: Vector3<f32>) -> u32 {
+        let (x, y, z) = (position.x, position.y, position.z);
 
         let obj = self.add_actor(x, y, z);
 
@@ -309,18 +805,161 @@ impl GameArea {
         self.actor_index.put(x, z, object_id);
         let actor = Actor::new(actor_type, object_id);
         let actor_id = actor.actor_id;
+        self.start_actor(actor);
+
+        return actor_id;
+    }
+
+    /// Starts the task running an actor's behavior
+    fn start_actor(&mut self, actor: Actor) {
+        let tx = self.game_tx.clone();
+        let actor_id = actor.actor_id;
         let handle_actor = actor.clone();
+        let definition = self.behaviors.for_actor(&actor.actor_type);
         self.actors.insert(actor_id, actor);
 
         let handle = tokio::spawn(async {
-            if let Err(e) = actor_main(handle_actor, tx).await {
-                log::error!("actor error: {}", e);
+            if let Err(e) = actor_main(handle_actor, definition, tx).await {
+                tracing::error!("actor error: {}", e);
             }
         });
 
         self.actor_handles.insert(actor_id, handle);
+    }
 
-        return actor_id;
+    /// Actors and items as they are now, players leave with their connections
+    pub fn snapshot(&self) -> Snapshot {
+        let actors: HashMap<u32, &Actor> = self
+            .actors
+            .values()
+            .map(|actor| (actor.object_id, actor))
+            .collect();
+
+        let objects = self
+            .objects
+            .values()
+            .filter(|obj| matches!(obj.object_type, ObjectType::Actor | ObjectType::Item))
+            .map(|obj| {
+                let frozen = self.freeze_game_object(obj);
+                SavedObject {
+                    object_id: obj.object_id,
+                    object_type: obj.object_type.clone(),
+                    alive: obj.alive,
+                    age: obj.age,
+                    health: obj.health,
+                    position: frozen.position,
+                    velocity: frozen.velocity,
+                    acceleration: frozen.acceleration,
+                    item: frozen.item,
+                    effects: frozen.effects,
+                    dead_for_ms: self
+                        .world
+                        .get::<Corpse>(obj.entity)
+                        .map(|corpse| corpse.died_at.elapsed().as_millis() as u64),
+                    actor: actors.get(&obj.object_id).map(|actor| SavedActor {
+                        actor_id: actor.actor_id,
+                        actor_type: actor.actor_type.clone(),
+                        zone: self.spawner.zone_of(actor.actor_id),
+                    }),
+                }
+            })
+            .collect();
+
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            seed: self.terrain.seed,
+            size: self.terrain.size,
+            ticks: self.ticks,
+            round: self.round.number,
+            objects,
+        }
+    }
+
+    fn restore_object(&mut self, saved: SavedObject) {
+        let mut entity = self.world.spawn((
+            Position {
+                value: saved.position,
+            },
+            Velocity {
+                value: saved.velocity,
+            },
+            Acceleration {
+                value: saved.acceleration,
+            },
+        ));
+        if saved.alive {
+            entity.insert(Alive);
+        }
+        if let Some(dead_for_ms) = saved.dead_for_ms {
+            let now = Instant::now();
+            entity.insert(Corpse {
+                died_at: now
+                    .checked_sub(Duration::from_millis(dead_for_ms))
+                    .unwrap_or(now),
+            });
+        }
+        if let Some(name) = saved.item {
+            entity.insert(Item { name });
+        }
+        if !saved.effects.is_empty() {
+            entity.insert(StatusEffects {
+                effects: saved.effects,
+                ..Default::default()
+            });
+        }
+        let entity = entity.id();
+
+        // new objects must not collide with restored ones
+        GAME_OBJECT_COUNTER.fetch_max(saved.object_id as i32 + 1, Ordering::Relaxed);
+        let object_id = saved.object_id;
+        self.entities.insert(entity, object_id);
+        self.objects.insert(
+            object_id,
+            GameObject {
+                alive: saved.alive,
+                age: saved.age,
+                object_id,
+                entity,
+                object_type: saved.object_type,
+                health: saved.health,
+            },
+        );
+
+        if let Some(saved_actor) = saved.actor {
+            let actor = Actor::restore(saved_actor.actor_id, saved_actor.actor_type, object_id);
+            if let Some(zone) = saved_actor.zone {
+                self.spawner.claim(actor.actor_id, zone);
+            }
+            if saved.alive {
+                self.actor_index
+                    .put(saved.position.x, saved.position.z, object_id);
+                self.start_actor(actor);
+            } else {
+                // corpses wait to be reaped like any other
+                self.actors.insert(actor.actor_id, actor);
+            }
+        }
+    }
+
+    pub fn save_snapshot(&mut self) {
+        self.last_snapshot = Instant::now();
+        let path = self.config.snapshot.path.clone();
+        let snapshot = self.snapshot();
+        match save_snapshot(&path, &snapshot) {
+            Ok(()) => tracing::info!(
+                objects = snapshot.objects.len(),
+                "saved snapshot {:?}",
+                path
+            ),
+            Err(e) => tracing::error!("error saving snapshot {:?}: {}", path, e),
+        }
+    }
+
+    fn update_snapshot(&mut self) {
+        let interval = self.config.snapshot.save_interval_ms;
+        if interval > 0 && self.last_snapshot.elapsed() >= Duration::from_millis(interval) {
+            self.save_snapshot();
+        }
     }
 
     pub fn populate(&mut self, num_items: u32, num_actors: u32) {
@@ -331,7 +970,10 @@ impl GameArea {
             let x = rng.gen::<f32>() * size;
             let y = 0.0;
             let z = rng.gen::<f32>() * size;
-            self.add_item(x, y, z);
+            if let Some(definition) = self.config.items.random_definition() {
+                let name = definition.name.clone();
+                self.add_item(&name, x, y, z);
+            }
         }
 
         for _n in 0..num_actors {
@@ -345,20 +987,8 @@ impl GameArea {
         client_conn: UnboundedSender<GameResponse>,
         username: String,
     ) {
-        if self.players.contains_key(&client.client_id) {
-            let result = client_conn.send(GameResponse::Error(1, "Incorrect hello".to_string()));
-            if let Err(e) = result {
-                log::error!("game response write error {:?}: {}", client, e);
-            }
-            return;
-        }
-
-        if self.has_username(&username) {
-            let result =
-                client_conn.send(GameResponse::Error(1, "Username already taken".to_string()));
-            if let Err(e) = result {
-                log::error!("game response write error {:?}: {}", client, e);
-            }
+        if let Err(error) = self.check_hello(&client, Some(&username)) {
+            GameArea::reject_hello(client, &client_conn, error);
             return;
         }
 
@@ -369,12 +999,15 @@ impl GameArea {
         let z = rng.gen::<f32>() * size;
         let player_obj = self.add_player(x, y, z);
         let player_object_id = player_obj.object_id;
+        let player_entity = player_obj.entity;
 
         let player = Player {
             client,
             conn: client_conn,
             username,
             object_id: player_obj.object_id,
+            last_fired: None,
+            death: None,
         };
 
         player.send(GameResponse::ElevationMap(
@@ -390,6 +1023,9 @@ impl GameArea {
 
         let notice = format!("Hello {}", player.username);
         player.send(GameResponse::Notice(notice));
+        if let Some(inventory) = self.world.get::<Inventory>(player_entity) {
+            player.send(GameResponse::Inventory(inventory.clone()));
+        }
         player.send(GameResponse::StateUpdate(StateUpdate {
             object_id: player_object_id,
             area_size: self.terrain.size,
@@ -401,35 +1037,206 @@ impl GameArea {
             incremental: false,
         }));
 
+        player.send(GameResponse::RoundStateChanged(
+            self.round.state,
+            self.round.remaining_ms(&self.config.mode),
+        ));
+
+        player.send(GameResponse::Leaderboard(self.scoreboard.leaderboard()));
+
+        self.scoreboard.join(player_object_id, &player.username);
         self.players.insert(client.client_id, player);
+        self.broadcast_object(player_object_id);
 
-        if let Some(player_obj) = self.objects.get(&player_object_id) {
-            for other in self.players.values() {
-                other.send(GameResponse::StateUpdate(StateUpdate {
-                    object_id: other.object_id,
-                    area_size: self.terrain.size,
-                    objects: vec![self.freeze_game_object(player_obj)],
-                    incremental: true,
-                }));
-            }
-        }
+        self.assign_team(player_object_id);
+        self.with_mode(|mode, area| mode.on_player_join(area, player_object_id));
     }
 
     async fn handle_goodbye(&mut self, client: Client) {
+        self.disconnect(client, None);
+    }
+
+    /// Removes a player or spectator, telling them why if the server sent them away
+    fn disconnect(&mut self, client: Client, reason: Option<String>) {
         if let Some(player) = self.players.remove(&client.client_id) {
+            self.combat.forget(player.object_id);
             if let Some(mut player_obj) = self.objects.remove(&player.object_id) {
                 player_obj.alive = false;
+                self.broadcast_frozen(self.freeze_game_object(&player_obj));
+            }
+            player.send(GameResponse::Goodbye(reason));
+
+            self.scoreboard.leave(player.object_id);
+            self.chat.forget(client.client_id);
+            self.with_mode(|mode, area| mode.on_player_leave(area, player.object_id));
+            self.rebalance_teams();
+        } else if let Some(spectator) = self.spectators.remove(&client.client_id) {
+            spectator.send(GameResponse::Goodbye(reason));
+        }
+    }
+
+    async fn handle_spectate(
+        &mut self,
+        client: Client,
+        client_conn: UnboundedSender<GameResponse>,
+        full_map: bool,
+    ) {
+        if let Err(error) = self.check_hello(&client, None) {
+            GameArea::reject_hello(client, &client_conn, error);
+            return;
+        }
+
+        let spectator = Spectator {
+            client,
+            conn: client_conn,
+            full_map,
+            following: None,
+        };
+
+        spectator.send(GameResponse::ElevationMap(
+            self.terrain.size,
+            self.terrain.size,
+            self.terrain.elevation_map.clone(),
+        ));
+        spectator.send(GameResponse::TerrainMap(
+            self.terrain.size,
+            self.terrain.size,
+            self.terrain.terrain_map.clone(),
+        ));
+        spectator.send(GameResponse::Notice("Spectating".to_string()));
+        spectator.send(GameResponse::RoundStateChanged(
+            self.round.state,
+            self.round.remaining_ms(&self.config.mode),
+        ));
+        spectator.send(GameResponse::Scoreboard(self.scoreboard.entries()));
+        spectator.send(GameResponse::Leaderboard(self.scoreboard.leaderboard()));
+
+        tracing::info!("spectator joined {:?}", client);
+        self.spectators.insert(client.client_id, spectator);
+        self.send_spectator_state(client.client_id);
+    }
+
+    async fn handle_follow(&mut self, client: Client, object_id: Option<u32>) {
+        let known = object_id.is_none_or(|object_id| self.objects.contains_key(&object_id));
+        let spectator = match self.spectators.get_mut(&client.client_id) {
+            Some(spectator) => spectator,
+            None => return,
+        };
+        if !known {
+            spectator.send(GameResponse::Error(5, "No such object".to_string()));
+            return;
+        }
+
+        spectator.following = object_id;
+        self.send_spectator_state(client.client_id);
+    }
+
+    /// Full state of everything a spectator can see, centered on whatever they follow
+    fn send_spectator_state(&self, client_id: u32) {
+        let spectator = match self.spectators.get(&client_id) {
+            Some(spectator) => spectator,
+            None => return,
+        };
+        let focus = self.spectator_focus(spectator);
+        let view_range = self.config.spectators.view_range;
+
+        spectator.send(GameResponse::StateUpdate(StateUpdate {
+            object_id: spectator.following.unwrap_or(0),
+            area_size: self.terrain.size,
+            objects: self
+                .objects
+                .values()
+                .map(|obj| self.freeze_game_object(obj))
+                .filter(|frozen| spectator.sees(focus.as_ref(), &frozen.position, view_range))
+                .collect(),
+            incremental: false,
+        }));
+    }
 
-                for other in self.players.values() {
-                    other.send(GameResponse::StateUpdate(StateUpdate {
-                        object_id: other.object_id,
-                        area_size: self.terrain.size,
-                        objects: vec![self.freeze_game_object(&player_obj)],
-                        incremental: true,
-                    }));
+    fn spectator_focus(&self, spectator: &Spectator) -> Option<Vector3<f32>> {
+        spectator
+            .following
+            .and_then(|object_id| self.objects.get(&object_id))
+            .and_then(|obj| self.world.get::<Position>(obj.entity))
+            .map(|position| position.value)
+    }
+
+    async fn handle_chat(&mut self, client: Client, channel: ChatChannel, text: String) {
+        let (object_id, username) = match self.players.get(&client.client_id) {
+            Some(player) => (player.object_id, player.username.clone()),
+            None => return,
+        };
+        let sender = &self.players[&client.client_id];
+
+        let text = match self.chat.check(client.client_id, &username, &channel, text) {
+            Ok(text) => text,
+            Err(reason) => {
+                sender.send(GameResponse::Error(4, reason));
+                return;
+            }
+        };
+
+        let recipients: Vec<&Player> = match &channel {
+            ChatChannel::Global => self.players.values().collect(),
+            ChatChannel::Team => match self.team_of(object_id) {
+                Some(team) => self
+                    .players
+                    .values()
+                    .filter(|other| self.team_of(other.object_id) == Some(team))
+                    .collect(),
+                None => {
+                    sender.send(GameResponse::Error(3, "Not on a team".to_string()));
+                    return;
+                }
+            },
+            ChatChannel::Proximity => {
+                let position = self
+                    .objects
+                    .get(&object_id)
+                    .and_then(|obj| self.world.get::<Position>(obj.entity));
+                let position = match position {
+                    Some(position) => position.value,
+                    None => return,
+                };
+                self.players
+                    .values()
+                    .filter(|other| {
+                        self.objects
+                            .get(&other.object_id)
+                            .and_then(|obj| self.world.get::<Position>(obj.entity))
+                            .is_some_and(|other_pos| {
+                                other_pos.value.metric_distance(&position)
+                                    <= self.chat.config.proximity_range
+                            })
+                    })
+                    .collect()
+            }
+            ChatChannel::Whisper(to) => {
+                match self
+                    .players
+                    .values()
+                    .find(|other| other.username.eq_ignore_ascii_case(to))
+                {
+                    Some(target) if target.client.client_id != client.client_id => {
+                        vec![sender, target]
+                    }
+                    Some(_) => vec![sender],
+                    None => {
+                        sender.send(GameResponse::Error(4, format!("No player named {}", to)));
+                        return;
+                    }
                 }
             }
-            player.send(GameResponse::Goodbye());
+        };
+
+        let response = GameResponse::ChatMessage(ChatMessage {
+            channel,
+            object_id,
+            username,
+            text,
+        });
+        for recipient in recipients {
+            recipient.send(response.clone());
         }
     }
 
@@ -440,24 +1247,69 @@ impl GameArea {
     }
 
     async fn handle_move(&mut self, client: Client, x: f32, y: f32, z: f32) {
-        if let Some(player) = self.players.get(&client.client_id) {
+        if let Some(player) = self
+            .players
+            .get(&client.client_id)
+            .filter(|player| player.death.is_none())
+        {
             if let Some(player_obj) = self.objects.get(&player.object_id) {
+                let speed = 10. * self.speed_factor(player_obj.object_id);
                 let mut entity = self.world.entity_mut(player_obj.entity);
                 let mut velocity = entity.get_mut::<Velocity>().unwrap();
-                velocity.value.x = 10. * x;
-                velocity.value.y = 10. * y;
-                velocity.value.z = 10. * z;
-
-                for other in self.players.values() {
-                    other.send(GameResponse::StateUpdate(StateUpdate {
-                        object_id: other.object_id,
-                        area_size: self.terrain.size as u32,
-                        incremental: true,
-                        objects: vec![self.freeze_game_object(player_obj)],
-                    }));
+                velocity.value.x = speed * x;
+                velocity.value.y = speed * y;
+                velocity.value.z = speed * z;
+
+                self.broadcast_frozen(self.freeze_game_object(player_obj));
+            }
+        }
+    }
+
+    async fn handle_fire(&mut self, client: Client, x: f32, y: f32, z: f32) {
+        let cooldown = Duration::from_millis(self.config.projectiles.cooldown_ms);
+        let (object_id, entity) = match self.players.get_mut(&client.client_id) {
+            Some(player) if player.death.is_none() => {
+                if player
+                    .last_fired
+                    .is_some_and(|last_fired| last_fired.elapsed() < cooldown)
+                {
+                    return;
+                }
+                player.last_fired = Some(Instant::now());
+                match self.objects.get(&player.object_id) {
+                    Some(player_obj) => (player_obj.object_id, player_obj.entity),
+                    None => return,
                 }
             }
+            _ => return,
+        };
+
+        let direction = Vector3::new(x, y, z);
+        if direction.magnitude() == 0.0 || direction.x.is_nan() {
+            return;
         }
+
+        let ammo_per_shot = self.config.projectiles.ammo_per_shot;
+        let inventory = match self.world.get_mut::<Inventory>(entity) {
+            Some(mut inventory) => {
+                if !inventory.take(ItemKind::Ammo, ammo_per_shot) {
+                    return;
+                }
+                inventory.clone()
+            }
+            None => return,
+        };
+        if ammo_per_shot > 0 {
+            if let Some(player) = self.players.get(&client.client_id) {
+                player.send(GameResponse::Inventory(inventory));
+            }
+        }
+
+        let position = self.world.get::<Position>(entity).unwrap().value;
+        let velocity = direction.normalize() * self.config.projectiles.speed;
+
+        let projectile_id = self.add_projectile(object_id, position, velocity).object_id;
+        self.broadcast_object(projectile_id);
     }
 
     fn query(
@@ -493,143 +1345,510 @@ impl GameArea {
         objects.iter().take(limit).cloned().collect()
     }
 
-    async fn handle_scan(
-        &mut self,
-        actor_id: u32,
-        response_conn: oneshot::Sender<(
-            Actor,
-            FrozenGameObject,
-            Vec<FrozenGameObject>,
-            Vec<FrozenGameObject>,
-        )>,
-    ) {
-        let actor = self.actors.get(&actor_id).unwrap();
+    async fn handle_scan(&mut self, actor_id: u32, response_conn: oneshot::Sender<Perception>) {
+        // dropping the response for a reaped actor lets its task wind down
+        let actor = match self.actors.get(&actor_id) {
+            Some(actor) => actor,
+            None => return,
+        };
         let actor_obj = self.objects.get(&actor.object_id).unwrap();
+        let params = self.config.flocking(&actor.actor_type);
         let actor_pos = self
             .world
             .entity(actor_obj.entity)
             .get::<Position>()
             .unwrap();
-        let actor_vel = self
-            .world
-            .entity(actor_obj.entity)
-            .get::<Velocity>()
-            .unwrap();
-        let actor_accel = self
-            .world
-            .entity(actor_obj.entity)
-            .get::<Acceleration>()
-            .unwrap();
 
-        let frozen_actor = FrozenGameObject {
-            object: actor_obj.clone(),
-            position: actor_pos.value,
-            velocity: actor_vel.value,
-            acceleration: actor_accel.value,
-        };
+        let frozen_actor = self.freeze_game_object(actor_obj);
 
         let players: Vec<FrozenGameObject> = self
             .players
             .values()
             .flat_map(|player| self.objects.get(&player.object_id))
+            .filter(|player| player.alive)
             .filter(|player| {
                 let player_pos = self.world.entity(player.entity).get::<Position>().unwrap();
-                player_pos.value.metric_distance(&actor_pos.value) < 100.0
+                player_pos.value.metric_distance(&actor_pos.value) < params.player_detection_range
             })
             .map(|player| self.freeze_game_object(player))
             .collect();
         let actors: Vec<FrozenGameObject> = self
-            .query(actor_id, &actor_obj, ObjectType::Actor, 20)
+            .query(
+                actor_id,
+                &actor_obj,
+                ObjectType::Actor,
+                params.neighbor_limit,
+            )
             .iter()
             .map(|actor| self.freeze_game_object(actor))
             .collect();
 
-        if let Err(e) = response_conn.send((actor.clone(), frozen_actor, players, actors)) {
-            log::error!("error sending response: {:?}", e);
+        let flow = players
+            .iter()
+            .min_by(|a, b| {
+                let dist_a = a.position.metric_distance(&actor_pos.value);
+                let dist_b = b.position.metric_distance(&actor_pos.value);
+                dist_a
+                    .partial_cmp(&dist_b)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .and_then(|target| {
+                self.flow_fields
+                    .sample(&self.pathfinder, target.object.object_id, &actor_pos.value)
+            });
+
+        let perception = Perception {
+            actor: actor.clone(),
+            object: frozen_actor,
+            players,
+            actors,
+            params,
+            flow,
+        };
+
+        if let Err(e) = response_conn.send(perception) {
+            tracing::error!("error sending response: {:?}", e);
         }
     }
 
     async fn handle_actor_move(&mut self, actor_id: u32, x: f32, y: f32, z: f32) {
         if let Some(actor) = self.actors.get(&actor_id) {
-            if let Some(actor_obj) = self.objects.get(&actor.object_id) {
-                // FIXME send impulse to actor
-
-                // log::debug!("accel: {:?} / vel: {:?} / pos: {:?}", actor_obj.acceleration, actor_obj.velocity, actor_obj.position);
-
-                for other in self.players.values() {
-                    other.send(GameResponse::StateUpdate(StateUpdate {
-                        object_id: other.object_id,
-                        area_size: self.terrain.size as u32,
-                        incremental: true,
-                        objects: vec![self.freeze_game_object(actor_obj)],
-                    }));
+            if let Some(actor_obj) = self.objects.get(&actor.object_id).filter(|obj| obj.alive) {
+                let position = self.world.get::<Position>(actor_obj.entity).unwrap().value;
+                let max_speed = MAX_ACTOR_SPEED * self.speed_factor(actor_obj.object_id);
+                if let Some(mut velocity) = self.world.get_mut::<Velocity>(actor_obj.entity) {
+                    velocity.value += Vector3::new(x, 0.0, z);
+                    if velocity.value.magnitude() > max_speed {
+                        velocity.value = velocity.value.normalize() * max_speed;
+                    }
+
+                    // don't let steering carry actors off into impassable terrain
+                    if !self.pathfinder.is_walkable(&(position + velocity.value)) {
+                        velocity.value = Vector3::new(0.0, 0.0, 0.0);
+                    }
                 }
+
+                // tracing::debug!("accel: {:?} / vel: {:?} / pos: {:?}", actor_obj.acceleration, actor_obj.velocity, actor_obj.position);
+
+                self.broadcast_frozen(self.freeze_game_object(actor_obj));
             }
         }
     }
 
-    async fn _handle_actor_death(&mut self, actor_id: u32) {
-        if let Some(handle) = self.actor_handles.remove(&actor_id) {
-            if let Err(e) = handle.await {
-                log::error!("error waiting for actor to reap: {}", e);
+    async fn handle_find_path(
+        &mut self,
+        actor_id: u32,
+        target: Vector3<f32>,
+        response_conn: oneshot::Sender<Option<Path>>,
+    ) {
+        let position = self
+            .actors
+            .get(&actor_id)
+            .and_then(|actor| self.objects.get(&actor.object_id))
+            .and_then(|actor_obj| self.world.get::<Position>(actor_obj.entity))
+            .map(|position| position.value);
+
+        let position = match position {
+            Some(position) => position,
+            None => {
+                if let Err(e) = response_conn.send(None) {
+                    tracing::error!("error sending path: {:?}", e);
+                }
+                return;
             }
-        }
-
-        let size = self.terrain.size as f32;
-        if let Some(actor) = self.actors.remove(&actor_id) {
-            if let Some(mut actor_obj) = self.objects.remove(&actor.object_id) {
-                actor_obj.alive = false;
+        };
 
-                for other in self.players.values() {
-                    other.send(GameResponse::StateUpdate(StateUpdate {
-                        object_id: other.object_id,
-                        area_size: size as u32,
-                        incremental: true,
-                        objects: vec![self.freeze_game_object(&actor_obj)],
-                    }));
-                }
+        // long searches would hold up the tick for everyone, so they run on the blocking pool
+        let pathfinder = self.pathfinder.clone();
+        tokio::task::spawn_blocking(move || {
+            let path = pathfinder.find_path(&position, &target);
+            if let Err(e) = response_conn.send(path) {
+                tracing::error!("error sending path: {:?}", e);
             }
+        });
+    }
+
+    pub fn broadcast(&self, response: GameResponse) {
+        for other in self.players.values() {
+            other.send(response.clone());
+        }
+        for spectator in self.spectators.values() {
+            spectator.send(response.clone());
         }
     }
 
-    async fn handle_respawn(&mut self, actor_id: u32) {
-        self._handle_actor_death(actor_id).await;
+    /// What a player's view is centered on, their killer while they are dead
+    fn view_object(&self, player: &Player) -> u32 {
+        player
+            .death
+            .and_then(|death| death.killer)
+            .filter(|killer| self.objects.contains_key(killer))
+            .unwrap_or(player.object_id)
+    }
 
-        let size = self.terrain.size as f32;
-        let new_actor_id = self.spawn_actor(ActorType::Walker);
+    pub fn broadcast_object(&self, object_id: u32) {
+        if let Some(obj) = self.objects.get(&object_id) {
+            self.broadcast_frozen(self.freeze_game_object(obj));
+        }
+    }
 
-        if let Some(new_object) = self.objects.get(&new_actor_id) {
-            for other in self.players.values() {
-                other.send(GameResponse::StateUpdate(StateUpdate {
-                    object_id: other.object_id,
-                    area_size: size as u32,
+    /// Sends an object to every player and to spectators who can see it
+    fn broadcast_frozen(&self, frozen: FrozenGameObject) {
+        for other in self.players.values() {
+            other.send(GameResponse::StateUpdate(StateUpdate {
+                object_id: self.view_object(other),
+                area_size: self.terrain.size,
+                incremental: true,
+                objects: vec![frozen.clone()],
+            }));
+        }
+
+        let view_range = self.config.spectators.view_range;
+        for spectator in self.spectators.values() {
+            let focus = self.spectator_focus(spectator);
+            if spectator.sees(focus.as_ref(), &frozen.position, view_range) {
+                spectator.send(GameResponse::StateUpdate(StateUpdate {
+                    object_id: spectator.following.unwrap_or(0),
+                    area_size: self.terrain.size,
                     incremental: true,
-                    objects: vec![self.freeze_game_object(new_object)],
+                    objects: vec![frozen.clone()],
                 }));
             }
         }
     }
 
-    async fn handle_die(&mut self, actor_id: u32) {
-        self._handle_actor_death(actor_id).await;
+    /// Stops an actor's task and leaves its body in the world until it is reaped
+    pub fn kill_actor(&mut self, actor_id: u32) {
+        if let Some(handle) = self.actor_handles.remove(&actor_id) {
+            handle.abort();
+        }
+
+        let object_id = match self.actors.get(&actor_id) {
+            Some(actor) => actor.object_id,
+            None => return,
+        };
+
+        let entity = match self.objects.get_mut(&object_id) {
+            Some(actor_obj) if actor_obj.alive => {
+                actor_obj.alive = false;
+                actor_obj.health = 0;
+                actor_obj.entity
+            }
+            _ => return,
+        };
+
+        let mut entity = self.world.entity_mut(entity);
+        entity.remove::<Alive>();
+        entity.insert((
+            Corpse {
+                died_at: Instant::now(),
+            },
+            Velocity {
+                value: Vector3::new(0.0, 0.0, 0.0),
+            },
+            Acceleration {
+                value: Vector3::new(0.0, 0.0, 0.0),
+            },
+        ));
+
+        self.broadcast_object(object_id);
+    }
+
+    /// Removes a dead actor for good, freeing its spawn slot after the respawn delay
+    fn reap_actor(&mut self, actor_id: u32) {
+        let actor = match self.actors.remove(&actor_id) {
+            Some(actor) => actor,
+            None => return,
+        };
+
+        if let Some(actor_obj) = self.objects.remove(&actor.object_id) {
+            self.entities.remove(&actor_obj.entity);
+            self.world.despawn(actor_obj.entity);
+        }
+        self.combat.forget(actor.object_id);
+
+        let delay = Duration::from_millis(self.config.lifecycle.respawn_delay_ms);
+        self.spawner.release(actor_id, Some(Instant::now() + delay));
+    }
+
+    async fn handle_reload_config(&mut self, config: Config) {
+        tracing::info!("config reloaded");
+        self.pathfinder = Arc::new(Pathfinder::new(&self.terrain, &config.pathfinding));
+        self.flow_fields = FlowFieldManager::new(&config.flow_fields);
+        self.behaviors = FsmDefinitions::new(&config.behaviors);
+        self.spawner.set_config(&config.spawner);
+        self.combat.set_config(&config.combat);
+        self.scoreboard.set_config(&config.scoreboard);
+        self.chat.set_config(&config.chat);
+        self.bans.set_config(&config.moderation);
+        let mode_changed = self.mode.as_ref().map(|mode| mode.kind()) != Some(config.mode.mode);
+        self.config = config;
+        if mode_changed {
+            self.set_mode(new_mode(self.config.mode.mode));
+        }
     }
 
     async fn handle_tick(&mut self, tick_time: Instant) {
         let now = Instant::now();
+        if self.paused {
+            // don't let the pause show up as one huge step when resumed
+            self.last_tick = now;
+            return;
+        }
         let delta = now - self.last_tick;
 
         if delta >= Duration::from_millis(16) {
             self.ticks += 1;
             self.tick(delta);
             self.last_tick = now;
-            let fps = self.fps_counter.tick();
+            self.tps = self.fps_counter.tick();
+
+            METRICS.tick_duration.observe(now.elapsed());
+            METRICS.tps.store(self.tps as u64, Ordering::Relaxed);
+            METRICS
+                .players
+                .store(self.players.len() as u64, Ordering::Relaxed);
+            METRICS
+                .spectators
+                .store(self.spectators.len() as u64, Ordering::Relaxed);
+            METRICS
+                .actors
+                .store(self.actors.len() as u64, Ordering::Relaxed);
             if self.ticks % 63 == 0 {
-                log::debug!("ticks: {}", fps);
+                tracing::debug!("ticks: {}", self.tps);
+            }
+        }
+    }
+
+    async fn handle_admin(
+        &mut self,
+        command: AdminCommand,
+        response_conn: oneshot::Sender<AdminReply>,
+    ) {
+        let reply = match command {
+            AdminCommand::ListPlayers => AdminReply::Players(
+                self.players
+                    .values()
+                    .map(|player| PlayerInfo {
+                        client_id: player.client.client_id,
+                        addr: player.client.addr.to_string(),
+                        username: player.username.clone(),
+                        object_id: player.object_id,
+                        alive: player.death.is_none(),
+                        team: self.team_of(player.object_id).map(|team| team.0),
+                    })
+                    .collect(),
+            ),
+            AdminCommand::Kick(client_id, reason) => self.kick(client_id, reason, false).await,
+            AdminCommand::Ban(client_id, reason) => self.kick(client_id, reason, true).await,
+            AdminCommand::Notice(text) => {
+                self.broadcast(GameResponse::Notice(text));
+                AdminReply::Ok
+            }
+            AdminCommand::SpawnActor(actor_type, position) => {
+                let actor_id = match position {
+                    Some((x, y, z)) => Some(self.spawn_actor_at(actor_type, Vector3::new(x, y, z))),
+                    None => self.spawn_actor(actor_type),
+                };
+                match actor_id {
+                    Some(actor_id) => {
+                        if let Some(actor) = self.actors.get(&actor_id) {
+                            self.broadcast_object(actor.object_id);
+                        }
+                        AdminReply::Spawned(actor_id)
+                    }
+                    None => AdminReply::Error("No valid spawn point".to_string()),
+                }
+            }
+            AdminCommand::DespawnActor(actor_id) => {
+                if self.actors.contains_key(&actor_id) {
+                    self.kill_actor(actor_id);
+                    self.reap_actor(actor_id);
+                    AdminReply::Ok
+                } else {
+                    AdminReply::Error(format!("No actor {}", actor_id))
+                }
             }
+            AdminCommand::SetMode(kind) => {
+                self.config.mode.mode = kind;
+                self.set_mode(new_mode(kind));
+                AdminReply::Ok
+            }
+            AdminCommand::Pause => {
+                tracing::info!("game paused");
+                self.paused = true;
+                AdminReply::Ok
+            }
+            AdminCommand::Resume => {
+                tracing::info!("game resumed");
+                self.paused = false;
+                AdminReply::Ok
+            }
+            AdminCommand::Stats => AdminReply::Stats(AreaStats {
+                ticks: self.ticks,
+                tps: self.tps,
+                paused: self.paused,
+                players: self.players.len(),
+                spectators: self.spectators.len(),
+                actors: self.actors.len(),
+                objects: self.objects.len(),
+                entities: self.entities.len(),
+                mode: self.mode.as_ref().map(|mode| mode.kind()),
+                round: self.round.number,
+                round_state: self.round.state,
+            }),
+        };
+
+        if let Err(e) = response_conn.send(reply) {
+            tracing::error!("error sending admin reply: {:?}", e);
         }
     }
 
+    /// Disconnects a player, optionally banning their username and address too
+    async fn kick(&mut self, client_id: u32, reason: Option<String>, ban: bool) -> AdminReply {
+        let (client, username) = match self.players.get(&client_id) {
+            Some(player) => (player.client, Some(player.username.clone())),
+            None => match self.spectators.get(&client_id) {
+                Some(spectator) => (spectator.client, None),
+                None => return AdminReply::Error(format!("No client {}", client_id)),
+            },
+        };
+        if ban {
+            self.bans.ban(username.as_deref(), client.addr.ip());
+        }
+
+        tracing::info!("{} {:?}", if ban { "banned" } else { "kicked" }, client);
+        let reason = reason.unwrap_or_else(|| if ban { "Banned" } else { "Kicked" }.to_string());
+        self.disconnect(client, Some(reason));
+        AdminReply::Ok
+    }
+
+    /// Sends everyone away and stops actors, after which `process` returns
+    async fn handle_shutdown(&mut self, reason: String, response_conn: oneshot::Sender<()>) {
+        tracing::info!(
+            players = self.players.len(),
+            spectators = self.spectators.len(),
+            "shutting down"
+        );
+
+        let clients: Vec<Client> = self
+            .players
+            .values()
+            .map(|player| player.client)
+            .chain(self.spectators.values().map(|spectator| spectator.client))
+            .collect();
+        for client in clients {
+            self.disconnect(client, Some(reason.clone()));
+        }
+
+        if self.config.snapshot.save_on_shutdown {
+            self.save_snapshot();
+        }
+        for (_, handle) in self.actor_handles.drain() {
+            handle.abort();
+        }
+        self.scoreboard.save();
+        self.shutting_down = true;
+
+        if response_conn.send(()).is_err() {
+            tracing::error!("error acknowledging shutdown");
+        }
+    }
+
+    fn send_reply<T: std::fmt::Debug>(response_conn: oneshot::Sender<T>, reply: T) {
+        if let Err(e) = response_conn.send(reply) {
+            tracing::error!("error sending api response: {:?}", e);
+        }
+    }
+
+    async fn handle_status(&mut self, response_conn: oneshot::Sender<ServerStatus>) {
+        let status = ServerStatus {
+            uptime_ms: self.started_at.elapsed().as_millis() as u64,
+            tps: self.tps,
+            paused: self.paused,
+            players: self.players.len(),
+            spectators: self.spectators.len(),
+            actors: self.actors.len(),
+            mode: self.mode.as_ref().map(|mode| mode.kind()),
+            round_state: self.round.state,
+        };
+        GameArea::send_reply(response_conn, status);
+    }
+
+    async fn handle_players(&mut self, response_conn: oneshot::Sender<Vec<PlayerSummary>>) {
+        let players = self
+            .players
+            .values()
+            .map(|player| PlayerSummary {
+                username: player.username.clone(),
+                object_id: player.object_id,
+                alive: player.death.is_none(),
+                team: self.team_of(player.object_id).map(|team| team.0),
+            })
+            .collect();
+        GameArea::send_reply(response_conn, players);
+    }
+
+    async fn handle_world(&mut self, response_conn: oneshot::Sender<WorldInfo>) {
+        let world = WorldInfo {
+            seed: self.terrain.seed,
+            size: self.terrain.size,
+            biomes: self.terrain.biome_histogram(),
+        };
+        GameArea::send_reply(response_conn, world);
+    }
+
+    async fn handle_terrain_map(&mut self, response_conn: oneshot::Sender<(u32, Vec<u8>)>) {
+        let terrain_map = (self.terrain.size, self.terrain.terrain_map.clone());
+        if response_conn.send(terrain_map).is_err() {
+            tracing::error!("error sending terrain map");
+        }
+    }
+
+    /// Span for handling a message, tagged with whoever it came from
+    fn message_span(&self, msg: &GameMessage) -> tracing::Span {
+        let span = tracing::debug_span!(
+            "handle_message",
+            message = msg.name(),
+            client_id = tracing::field::Empty,
+            actor_id = tracing::field::Empty,
+            object_id = tracing::field::Empty,
+        );
+
+        match msg {
+            GameMessage::Hello(client, ..)
+            | GameMessage::Spectate(client, ..)
+            | GameMessage::Follow(client, ..)
+            | GameMessage::Goodbye(client)
+            | GameMessage::Ping(client, ..)
+            | GameMessage::Move(client, ..)
+            | GameMessage::Fire(client, ..)
+            | GameMessage::RequestRespawn(client)
+            | GameMessage::Chat(client, ..) => {
+                span.record("client_id", client.client_id);
+                if let Some(player) = self.players.get(&client.client_id) {
+                    span.record("object_id", player.object_id);
+                }
+            }
+            GameMessage::Scan(actor_id, ..)
+            | GameMessage::ActorMove(actor_id, ..)
+            | GameMessage::FindPath(actor_id, ..) => {
+                span.record("actor_id", *actor_id);
+                if let Some(actor) = self.actors.get(actor_id) {
+                    span.record("object_id", actor.object_id);
+                }
+            }
+            _ => {}
+        }
+        span
+    }
+
     pub async fn handle_message(&mut self, msg: GameMessage) {
+        let span = self.message_span(&msg);
+        self.dispatch_message(msg).instrument(span).await;
+    }
+
+    async fn dispatch_message(&mut self, msg: GameMessage) {
         match msg {
             GameMessage::Hello(client, client_conn, username) => {
                 self.handle_hello(client, client_conn, username).await;
@@ -643,17 +1862,50 @@ impl GameArea {
             GameMessage::Move(client, x, y, z) => {
                 self.handle_move(client, x, y, z).await;
             }
+            GameMessage::Fire(client, x, y, z) => {
+                self.handle_fire(client, x, y, z).await;
+            }
+            GameMessage::RequestRespawn(client) => {
+                self.handle_request_respawn(client).await;
+            }
+            GameMessage::Spectate(client, client_conn, full_map) => {
+                self.handle_spectate(client, client_conn, full_map).await;
+            }
+            GameMessage::Follow(client, object_id) => {
+                self.handle_follow(client, object_id).await;
+            }
+            GameMessage::Chat(client, channel, text) => {
+                self.handle_chat(client, channel, text).await;
+            }
             GameMessage::Scan(actor_id, response_conn) => {
                 self.handle_scan(actor_id, response_conn).await;
             }
             GameMessage::ActorMove(actor_id, x, y, z) => {
                 self.handle_actor_move(actor_id, x, y, z).await;
             }
-            GameMessage::Respawn(actor_id) => {
-                self.handle_respawn(actor_id).await;
+            GameMessage::FindPath(actor_id, target, response_conn) => {
+                self.handle_find_path(actor_id, target, response_conn).await;
+            }
+            GameMessage::Admin(command, response_conn) => {
+                self.handle_admin(command, response_conn).await;
+            }
+            GameMessage::Shutdown(reason, response_conn) => {
+                self.handle_shutdown(reason, response_conn).await;
+            }
+            GameMessage::Status(response_conn) => {
+                self.handle_status(response_conn).await;
             }
-            GameMessage::Die(actor_id) => {
-                self.handle_die(actor_id).await;
+            GameMessage::Players(response_conn) => {
+                self.handle_players(response_conn).await;
+            }
+            GameMessage::World(response_conn) => {
+                self.handle_world(response_conn).await;
+            }
+            GameMessage::TerrainMap(response_conn) => {
+                self.handle_terrain_map(response_conn).await;
+            }
+            GameMessage::ReloadConfig(config) => {
+                self.handle_reload_config(*config).await;
             }
             GameMessage::Tick(tick_time) => {
                 self.handle_tick(tick_time).await;
@@ -661,9 +1913,47 @@ impl GameArea {
         }
     }
 
+    #[tracing::instrument(level = "debug", skip_all, fields(tick = self.ticks))]
     pub fn tick(&mut self, elapsed: Duration) {
+        self.world.insert_resource(TickDelta(elapsed));
         self.schedule.run(&mut self.world);
 
+        let targets: Vec<(u32, Vector3<f32>)> = self
+            .players
+            .values()
+            .flat_map(|player| self.objects.get(&player.object_id))
+            .filter(|player_obj| player_obj.alive)
+            .flat_map(|player_obj| {
+                self.world
+                    .get::<Position>(player_obj.entity)
+                    .map(|position| (player_obj.object_id, position.value))
+            })
+            .collect();
+        self.flow_fields.update(&self.pathfinder, &targets);
+
+        self.update_projectiles();
+        self.update_melee();
+        self.update_effects();
+        self.update_pickups();
+        self.update_items();
+        self.update_lifecycle();
+        self.update_respawns();
+        self.update_spawner();
+        self.update_round();
+        self.update_scoreboard();
+        self.update_snapshot();
+
+        // actors have moved, so rebuild the index that scans search
+        self.actor_index.clear();
+        for actor in self.actors.values() {
+            if let Some(actor_obj) = self.objects.get(&actor.object_id).filter(|obj| obj.alive) {
+                if let Some(position) = self.world.get::<Position>(actor_obj.entity) {
+                    self.actor_index
+                        .put(position.value.x, position.value.z, actor_obj.object_id);
+                }
+            }
+        }
+
         self.objects.values_mut().for_each(|obj| {
             // if is_actor {
             //     self.actor_index.remove(obj.position.x, obj.position.z, obj.object_id);
@@ -700,17 +1990,922 @@ impl GameArea {
             //     self.actor_index.put(obj.position.x, obj.position.z, obj.object_id);
             // }
 
-            // log::debug!("pos: {:?} | vel: {:?}", obj.position, obj.velocity);
+            // tracing::debug!("pos: {:?} | vel: {:?}", obj.position, obj.velocity);
 
             obj.age += 1;
         });
     }
 
+    fn update_lifecycle(&mut self) {
+        let linger = Duration::from_millis(self.config.lifecycle.corpse_linger_ms);
+        let expired: Vec<u32> = self
+            .actors
+            .values()
+            .filter(|actor| {
+                self.objects
+                    .get(&actor.object_id)
+                    .and_then(|obj| self.world.get::<Corpse>(obj.entity))
+                    .is_some_and(|corpse| corpse.died_at.elapsed() >= linger)
+            })
+            .map(|actor| actor.actor_id)
+            .collect();
+        for actor_id in expired {
+            self.reap_actor(actor_id);
+        }
+    }
+
+    /// Expires projectiles and resolves what they ran into this tick
+    fn update_projectiles(&mut self) {
+        let mut query = self
+            .world
+            .query::<(Entity, &Position, &Velocity, &Projectile)>();
+        let projectiles: Vec<(Entity, Vector3<f32>, Vector3<f32>, Projectile)> = query
+            .iter(&self.world)
+            .map(|(entity, position, velocity, projectile)| {
+                (entity, position.value, velocity.value, *projectile)
+            })
+            .collect();
+
+        let size = self.terrain.size as f32;
+        let hit_radius = self.config.projectiles.hit_radius;
+
+        for (entity, position, velocity, projectile) in projectiles {
+            let projectile_id = match self.entities.get(&entity) {
+                Some(object_id) => *object_id,
+                None => continue,
+            };
+
+            let out_of_bounds =
+                position.x < 0.0 || position.z < 0.0 || position.x >= size || position.z >= size;
+            let hit_terrain = self.pathfinder.is_cliff(&(position - velocity), &position);
+
+            if projectile.ticks_left == 0 || out_of_bounds || hit_terrain {
+                self.despawn_object(projectile_id);
+                continue;
+            }
+
+            let target = self
+                .actor_index
+                .get_nearby(position.x, position.z, hit_radius * 2.0)
+                .into_iter()
+                .chain(self.players.values().map(|player| player.object_id))
+                .filter(|object_id| *object_id != projectile.owner)
+                .flat_map(|object_id| self.objects.get(&object_id))
+                .filter(|obj| obj.alive)
+                .find(|obj| {
+                    self.world
+                        .get::<Position>(obj.entity)
+                        .is_some_and(|target_pos| {
+                            target_pos.value.metric_distance(&position) <= hit_radius
+                        })
+                })
+                .map(|obj| obj.object_id);
+
+            if let Some(target_id) = target {
+                self.apply_damage(
+                    target_id,
+                    Damage {
+                        source: projectile.owner,
+                        kind: DamageKind::Projectile,
+                        amount: projectile.damage,
+                    },
+                );
+                self.despawn_object(projectile_id);
+            }
+        }
+    }
+
+    /// Actors touching a player hit them, each at most once per melee cooldown
+    fn update_melee(&mut self) {
+        let melee_range = self.combat.config.melee_range;
+        let attackers: HashMap<u32, ActorType> = self
+            .actors
+            .values()
+            .map(|actor| (actor.object_id, actor.actor_type.clone()))
+            .collect();
+
+        let mut hits = Vec::new();
+        for player in self.players.values() {
+            let player_pos = match self
+                .objects
+                .get(&player.object_id)
+                .filter(|obj| obj.alive)
+                .and_then(|obj| self.world.get::<Position>(obj.entity))
+            {
+                Some(position) => position.value,
+                None => continue,
+            };
+
+            for object_id in self
+                .actor_index
+                .get_nearby(player_pos.x, player_pos.z, melee_range)
+            {
+                let in_reach = self
+                    .objects
+                    .get(&object_id)
+                    .filter(|obj| obj.alive)
+                    .and_then(|obj| self.world.get::<Position>(obj.entity))
+                    .is_some_and(|position| {
+                        position.value.metric_distance(&player_pos) <= melee_range
+                    });
+                if !in_reach {
+                    continue;
+                }
+
+                let amount = attackers
+                    .get(&object_id)
+                    .map_or(0, |actor_type| self.combat.melee_damage(actor_type));
+                if amount > 0 {
+                    hits.push((player.object_id, object_id, amount));
+                }
+            }
+        }
+
+        for (target_id, source, amount) in hits {
+            if !self.combat.melee_ready(source) {
+                continue;
+            }
+            let hit = self.apply_damage(
+                target_id,
+                Damage {
+                    source,
+                    kind: DamageKind::Melee,
+                    amount,
+                },
+            );
+
+            let effect = attackers
+                .get(&source)
+                .and_then(|actor_type| self.combat.config.melee_effects.get(actor_type))
+                .cloned();
+            if let (Some(_), Some(effect)) = (hit, effect) {
+                self.apply_effect(target_id, effect, Some(source));
+            }
+        }
+    }
+
+    /// Runs a hit through the combat rules and tells everyone what it did
+    fn apply_damage(&mut self, target_id: u32, mut damage: Damage) -> Option<Hit> {
+        if self.is_friendly(damage.source, target_id) {
+            return None;
+        }
+
+        let target_actor = self
+            .actors
+            .values()
+            .find(|actor| actor.object_id == target_id)
+            .map(|actor| (actor.actor_id, actor.actor_type.clone()));
+        let armor = self
+            .combat
+            .armor(target_actor.as_ref().map(|(_, actor_type)| actor_type));
+
+        let damage_factor = self
+            .status_effects(damage.source)
+            .map_or(1.0, |status| status.damage_factor());
+        let shield_factor = self
+            .status_effects(target_id)
+            .map_or(1.0, |status| status.shield_factor());
+        let amount = damage.amount as f32 * damage_factor * shield_factor;
+        damage.amount = amount.round().clamp(0.0, u8::MAX as f32) as u8;
+
+        let hit = match self.objects.get_mut(&target_id) {
+            Some(target) if target.alive => {
+                match self
+                    .combat
+                    .resolve(target_id, target.health, &armor, &damage)
+                {
+                    Some(hit) => {
+                        target.health = hit.health;
+                        hit
+                    }
+                    None => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        self.broadcast(GameResponse::Damaged(
+            target_id,
+            damage.source,
+            hit.amount,
+            hit.health,
+        ));
+
+        let by_player = damage.source != target_id && self.is_player(damage.source);
+        if by_player {
+            self.scoreboard.damage(damage.source, hit.amount);
+        }
+
+        if !hit.killed {
+            return Some(hit);
+        }
+
+        if by_player {
+            self.scoreboard.kill(damage.source);
+        }
+
+        self.broadcast(GameResponse::Killed(target_id, damage.source));
+
+        match target_actor {
+            Some((actor_id, _)) => self.kill_actor(actor_id),
+            None => self.kill_player(target_id, Some(damage.source)),
+        }
+
+        if self.round.state == RoundState::Playing {
+            self.pending_kills.push((target_id, damage.source));
+            self.score_pending_kills();
+        }
+        Some(hit)
+    }
+
+    /// Leaves a player's body where it fell and starts their respawn timer
+    fn kill_player(&mut self, object_id: u32, killer: Option<u32>) {
+        let player = match self
+            .players
+            .values_mut()
+            .find(|player| player.object_id == object_id)
+        {
+            Some(player) if player.death.is_none() => player,
+            _ => return,
+        };
+        player.death = Some(Death {
+            killer,
+            died_at: Instant::now(),
+        });
+        player.send(GameResponse::Died(killer, self.config.respawn.delay_ms));
+        self.scoreboard.died(object_id, true);
+
+        let entity = match self.objects.get_mut(&object_id) {
+            Some(player_obj) => {
+                player_obj.alive = false;
+                player_obj.health = 0;
+                player_obj.entity
+            }
+            None => return,
+        };
+
+        let mut entity = self.world.entity_mut(entity);
+        entity.remove::<Alive>();
+        entity.insert((
+            Velocity {
+                value: Vector3::new(0.0, 0.0, 0.0),
+            },
+            Acceleration {
+                value: Vector3::new(0.0, 0.0, 0.0),
+            },
+        ));
+
+        self.broadcast_object(object_id);
+    }
+
+    async fn handle_request_respawn(&mut self, client: Client) {
+        if let Err(error) = self.respawn_player(client.client_id) {
+            if let Some(player) = self.players.get(&client.client_id) {
+                player.send(GameResponse::RespawnRejected(error));
+            }
+        }
+    }
+
+    /// Brings back dead players whose wait is over, retrying next tick if there was nowhere to put them
+    fn update_respawns(&mut self) {
+        let delay = Duration::from_millis(self.config.respawn.delay_ms);
+        let ready: Vec<u32> = self
+            .players
+            .values()
+            .filter(|player| {
+                player
+                    .death
+                    .is_some_and(|death| death.died_at.elapsed() >= delay)
+            })
+            .map(|player| player.client.client_id)
+            .collect();
+
+        for client_id in ready {
+            if let Err(error) = self.respawn_player(client_id) {
+                tracing::trace!(client_id, "respawn deferred: {:?}", error);
+            }
+        }
+    }
+
+    fn respawn_player(&mut self, client_id: u32) -> Result<(), RespawnError> {
+        let delay = Duration::from_millis(self.config.respawn.delay_ms);
+        let (object_id, death) = match self.players.get(&client_id) {
+            Some(player) => match player.death {
+                Some(death) => (player.object_id, death),
+                None => return Err(RespawnError::NotDead),
+            },
+            None => return Ok(()),
+        };
+
+        let state = self.round.state;
+        if !self
+            .mode
+            .as_ref()
+            .is_some_and(|mode| mode.allows_respawn(state))
+        {
+            return Err(RespawnError::RoundInProgress);
+        }
+
+        let waited = death.died_at.elapsed();
+        if waited < delay {
+            return Err(RespawnError::TooSoon((delay - waited).as_millis() as u64));
+        }
+
+        let position = self
+            .find_respawn_point()
+            .ok_or(RespawnError::NoSpawnPoint)?;
+        let entity = match self.objects.get_mut(&object_id) {
+            Some(player_obj) => {
+                player_obj.alive = true;
+                player_obj.health = 100;
+                player_obj.entity
+            }
+            None => return Ok(()),
+        };
+        self.world
+            .entity_mut(entity)
+            .insert((Alive, Position { value: position }))
+            .remove::<StatusEffects>();
+        self.combat.forget(object_id);
+
+        if let Some(player) = self.players.get_mut(&client_id) {
+            player.death = None;
+            player.send(GameResponse::Respawned(object_id));
+        }
+        self.scoreboard.respawned(object_id);
+        self.broadcast_object(object_id);
+        Ok(())
+    }
+
+    /// Somewhere walkable, preferably clear of living actors
+    fn find_respawn_point(&self) -> Option<Vector3<f32>> {
+        let enemies = self.actor_positions();
+        self.spawner
+            .find_clear_point(
+                &self.terrain,
+                &self.pathfinder,
+                &enemies,
+                self.config.respawn.enemy_clearance,
+            )
+            .or_else(|| {
+                self.spawner
+                    .find_clear_point(&self.terrain, &self.pathfinder, &[], 0.0)
+            })
+    }
+
+    /// Deals built up poison damage and broadcasts effects that ran out
+    fn update_effects(&mut self) {
+        let mut query = self.world.query::<(Entity, &mut StatusEffects)>();
+        let mut changed = Vec::new();
+        for (entity, mut status) in query.iter_mut(&mut self.world) {
+            let poison = status.pending_poison.floor();
+            if poison < 1.0 && !status.expired {
+                continue;
+            }
+
+            status.pending_poison -= poison;
+            status.expired = false;
+            let poisoner = status
+                .get(EffectKind::Poison)
+                .and_then(|effect| effect.source);
+            changed.push((entity, poison as u8, poisoner));
+        }
+
+        for (entity, poison, poisoner) in changed {
+            let object_id = match self.entities.get(&entity) {
+                Some(object_id) => *object_id,
+                None => continue,
+            };
+
+            if poison > 0 {
+                self.apply_damage(
+                    object_id,
+                    Damage {
+                        source: poisoner.unwrap_or(object_id),
+                        kind: DamageKind::Poison,
+                        amount: poison,
+                    },
+                );
+            }
+            self.broadcast_object(object_id);
+        }
+    }
+
+    /// Players walking over an item pick it up if they have any use for it
+    fn update_pickups(&mut self) {
+        let pickup_radius = self.config.items.pickup_radius;
+        let mut query = self.world.query::<(Entity, &Position, &Item)>();
+        let items: Vec<(Entity, Vector3<f32>, String)> = query
+            .iter(&self.world)
+            .map(|(entity, position, item)| (entity, position.value, item.name.clone()))
+            .collect();
+
+        for (entity, position, name) in items {
+            let item_id = match self.entities.get(&entity) {
+                Some(object_id) => *object_id,
+                None => continue,
+            };
+            let definition = match self.config.items.definition(&name) {
+                Some(definition) => definition.clone(),
+                None => continue,
+            };
+
+            let picker = self
+                .players
+                .values()
+                .filter(|player| player.death.is_none())
+                .flat_map(|player| {
+                    self.objects
+                        .get(&player.object_id)
+                        .map(|player_obj| (player.client.client_id, player_obj.entity))
+                })
+                .find(|(_, player_entity)| {
+                    self.world
+                        .get::<Position>(*player_entity)
+                        .is_some_and(|player_pos| {
+                            player_pos.value.metric_distance(&position) <= pickup_radius
+                        })
+                });
+            let (client_id, player_entity) = match picker {
+                Some(picker) => picker,
+                None => continue,
+            };
+            let object_id = self.players[&client_id].object_id;
+
+            let picked_up = match (definition.effect, definition.kind) {
+                (Some(effect), _) => {
+                    self.apply_effect(object_id, effect, None);
+                    true
+                }
+                (None, ItemKind::HealthPack) => match self.objects.get_mut(&object_id) {
+                    Some(player_obj) if player_obj.health < 100 => {
+                        let healed = player_obj.health as u32 + definition.amount;
+                        player_obj.health = healed.min(100) as u8;
+                        true
+                    }
+                    _ => false,
+                },
+                (None, kind) => {
+                    let limit = self.config.items.carry_limit(kind);
+                    match self.world.get_mut::<Inventory>(player_entity) {
+                        Some(mut inventory) => {
+                            let added = inventory.add(kind, definition.amount, limit);
+                            if added > 0 {
+                                let player = &self.players[&client_id];
+                                player.send(GameResponse::Inventory(inventory.clone()));
+                            }
+                            added > 0
+                        }
+                        None => false,
+                    }
+                }
+            };
+
+            if picked_up {
+                self.scoreboard.pickup(object_id);
+                self.despawn_object(item_id);
+                self.broadcast_object(object_id);
+            }
+        }
+    }
+
+    /// Restocks the world with items as they get picked up
+    fn update_items(&mut self) {
+        let mut query = self.world.query::<&Item>();
+        let count = query.iter(&self.world).count() as u32;
+        let missing = self.config.items.population.saturating_sub(count) as usize;
+
+        let players = self.player_positions();
+        for _ in 0..missing.min(self.config.items.max_spawns_per_tick) {
+            let name = match self.config.items.random_definition() {
+                Some(definition) => definition.name.clone(),
+                None => return,
+            };
+            let position = match self.spawner.find_clear_point(
+                &self.terrain,
+                &self.pathfinder,
+                &players,
+                self.config.items.pickup_radius * 2.0,
+            ) {
+                Some(position) => position,
+                None => continue,
+            };
+
+            let item_id = self
+                .add_item(&name, position.x, position.y, position.z)
+                .object_id;
+            self.broadcast_object(item_id);
+        }
+    }
+
+    fn update_spawner(&mut self) {
+        let players = self.player_positions();
+        let mut occupied = self.actor_positions();
+
+        for (zone, actor_type) in self.spawner.due_spawns() {
+            let position = match self.spawner.find_spawn_point(
+                &self.terrain,
+                &self.pathfinder,
+                self.spawner.zone_biomes(zone),
+                &players,
+                &occupied,
+            ) {
+                Some(position) => position,
+                None => continue,
+            };
+
+            let actor_id = self.spawn_actor_at(actor_type, position);
+            self.spawner.claim(actor_id, zone);
+            occupied.push(position);
+
+            if let Some(actor) = self.actors.get(&actor_id) {
+                self.broadcast_object(actor.object_id);
+            }
+        }
+    }
+
+    fn update_scoreboard(&mut self) {
+        if let Some(entries) = self.scoreboard.due_push() {
+            self.broadcast(GameResponse::Scoreboard(entries));
+        }
+        self.scoreboard.save_if_due();
+    }
+
+    /// Runs a mode hook with the mode taken out, then scores any kills the hook caused
+    fn with_mode<R>(&mut self, f: impl FnOnce(&mut dyn GameMode, &mut GameArea) -> R) -> Option<R> {
+        let mut mode = self.mode.take()?;
+        let result = f(mode.as_mut(), self);
+        self.mode = Some(mode);
+        self.score_pending_kills();
+        Some(result)
+    }
+
+    /// Hands queued kills to the mode, leaving them queued while one of its hooks is running
+    fn score_pending_kills(&mut self) {
+        if self.mode.is_none() {
+            return;
+        }
+        for (victim, killer) in std::mem::take(&mut self.pending_kills) {
+            self.with_mode(|mode, area| mode.on_kill(area, victim, killer));
+        }
+    }
+
+    /// Swaps in a different game mode, abandoning the round in progress
+    pub fn set_mode(&mut self, mode: Box<dyn GameMode>) {
+        if self.round.state == RoundState::Playing {
+            self.with_mode(|mode, area| mode.on_round_end(area));
+        }
+
+        tracing::info!("game mode is now {:?}", mode.kind());
+        let player_ids: Vec<u32> = self
+            .players
+            .values()
+            .map(|player| player.object_id)
+            .collect();
+        for object_id in &player_ids {
+            self.set_team(*object_id, None);
+        }
+        self.mode = Some(mode);
+        for object_id in player_ids {
+            self.assign_team(object_id);
+            self.with_mode(|mode, area| mode.on_player_join(area, object_id));
+        }
+        self.enter_round_state(RoundState::Lobby);
+    }
+
+    fn enter_round_state(&mut self, state: RoundState) {
+        self.round.enter(state);
+        tracing::info!("round {} is now {:?}", self.round.number, state);
+        self.broadcast(GameResponse::RoundStateChanged(
+            state,
+            self.round.remaining_ms(&self.config.mode),
+        ));
+    }
+
+    /// Moves the round through lobby, warmup, playing and post game
+    fn update_round(&mut self) {
+        let has_rounds = match self.mode.as_ref() {
+            Some(mode) => mode.has_rounds(),
+            None => return,
+        };
+        let config = &self.config.mode;
+        let enough_players = !self.players.is_empty() && self.players.len() >= config.min_players;
+        let elapsed = self.round.elapsed();
+
+        match self.round.state {
+            RoundState::Lobby if enough_players => {
+                self.enter_round_state(RoundState::Warmup);
+            }
+            RoundState::Warmup if !enough_players => {
+                self.enter_round_state(RoundState::Lobby);
+            }
+            RoundState::Warmup
+                if !has_rounds || elapsed >= Duration::from_millis(config.warmup_ms) =>
+            {
+                self.enter_round_state(RoundState::Playing);
+                self.broadcast(GameResponse::RoundStarted(self.round.number));
+                self.with_mode(|mode, area| mode.on_round_start(area));
+            }
+            RoundState::Playing => {
+                self.with_mode(|mode, area| mode.on_tick(area));
+
+                if !has_rounds {
+                    return;
+                }
+
+                let outcome = match self.mode.as_ref() {
+                    Some(mode) => mode.check_victory(self),
+                    None => None,
+                };
+                if let Some(outcome) = outcome {
+                    tracing::info!("round {} ended: {:?}", self.round.number, outcome);
+                    self.with_mode(|mode, area| mode.on_round_end(area));
+                    self.broadcast(GameResponse::RoundEnded(self.round.number, outcome));
+                    self.enter_round_state(RoundState::PostGame);
+                }
+            }
+            RoundState::PostGame if elapsed >= Duration::from_millis(config.post_game_ms) => {
+                self.enter_round_state(RoundState::Lobby);
+            }
+            _ => {}
+        }
+    }
+
     pub async fn process(&mut self, mut game_rx: UnboundedReceiver<GameMessage>) {
-        loop {
-            if let Some(msg) = game_rx.recv().await {
-                self.handle_message(msg).await;
+        while let Some(msg) = game_rx.recv().await {
+            METRICS
+                .queue_depth
+                .store(game_rx.len() as u64, Ordering::Relaxed);
+            self.handle_message(msg).await;
+            if self.shutting_down {
+                break;
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use crate::deathmatch::Deathmatch;
+    use crate::mode::ModeKind;
+
+    fn area() -> GameArea {
+        let (game_tx, _) = unbounded_channel();
+        GameArea::new(64, Config::default(), game_tx)
+    }
+
+    /// Puts a player in the world, returning their client, object and what they are sent
+    fn join(area: &mut GameArea, client_id: u32) -> (Client, u32, UnboundedReceiver<GameResponse>) {
+        let (conn, responses) = unbounded_channel();
+        let client = Client {
+            client_id,
+            addr: "127.0.0.1:3030".parse().unwrap(),
+        };
+        let object_id = area.add_player(10.0, 0.0, 10.0).object_id;
+        area.players.insert(
+            client_id,
+            Player {
+                client,
+                conn,
+                username: format!("player{}", client_id),
+                object_id,
+                last_fired: None,
+                death: None,
+            },
+        );
+        (client, object_id, responses)
+    }
+
+    fn received(responses: &mut UnboundedReceiver<GameResponse>) -> Vec<GameResponse> {
+        let mut received = Vec::new();
+        while let Ok(response) = responses.try_recv() {
+            received.push(response);
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn players_respawn_once_the_delay_is_up() {
+        let mut area = area();
+        let (client, object_id, mut responses) = join(&mut area, 1);
+
+        area.kill_player(object_id, Some(99));
+        area.handle_request_respawn(client).await;
+        area.update_respawns();
+        assert!(area.players[&client.client_id].death.is_some());
+        assert!(!area.objects[&object_id].alive);
+        let sent = received(&mut responses);
+        assert!(sent
+            .iter()
+            .any(|response| matches!(response, GameResponse::Died(Some(99), _))));
+        assert!(sent.iter().any(|response| matches!(
+            response,
+            GameResponse::RespawnRejected(RespawnError::TooSoon(_))
+        )));
+
+        let delay = Duration::from_millis(area.config.respawn.delay_ms);
+        let player = area.players.get_mut(&client.client_id).unwrap();
+        player.death.as_mut().unwrap().died_at -= delay;
+        area.update_respawns();
+
+        assert!(area.players[&client.client_id].death.is_none());
+        assert!(area.objects[&object_id].alive);
+        assert_eq!(area.objects[&object_id].health, 100);
+        assert!(received(&mut responses)
+            .iter()
+            .any(|response| matches!(response, GameResponse::Respawned(id) if *id == object_id)));
+    }
+
+    #[tokio::test]
+    async fn respawn_is_refused_while_alive() {
+        let mut area = area();
+        let (client, _, mut responses) = join(&mut area, 1);
+
+        area.handle_request_respawn(client).await;
+        assert!(received(&mut responses).iter().any(|response| matches!(
+            response,
+            GameResponse::RespawnRejected(RespawnError::NotDead)
+        )));
+    }
+
+    #[test]
+    fn players_pick_up_items_in_reach() {
+        let mut area = area();
+        let (_, object_id, mut responses) = join(&mut area, 1);
+        let near = area.add_item("ammo_box", 11.0, 0.0, 10.0).object_id;
+        let far = area.add_item("ammo_box", 40.0, 0.0, 40.0).object_id;
+
+        area.update_pickups();
+
+        assert!(!area.objects.contains_key(&near));
+        assert!(area.objects.contains_key(&far));
+        let entity = area.objects[&object_id].entity;
+        let inventory = area.world.get::<Inventory>(entity).unwrap();
+        assert_eq!(inventory.counts[&ItemKind::Ammo], 70);
+        assert!(received(&mut responses)
+            .iter()
+            .any(|response| matches!(response, GameResponse::Inventory(_))));
+    }
+
+    #[test]
+    fn health_packs_are_left_for_players_at_full_health() {
+        let mut area = area();
+        let (_, object_id, _responses) = join(&mut area, 1);
+        let pack = area.add_item("health_pack", 10.0, 0.0, 11.0).object_id;
+
+        area.update_pickups();
+        assert!(area.objects.contains_key(&pack));
+
+        area.objects.get_mut(&object_id).unwrap().health = 50;
+        area.update_pickups();
+        assert!(!area.objects.contains_key(&pack));
+        assert_eq!(area.objects[&object_id].health, 75);
+    }
+
+    /// An area running `kind` whose rounds start as soon as anyone joins
+    fn mode_area(kind: ModeKind) -> GameArea {
+        let mut config = Config::default();
+        config.mode.mode = kind;
+        config.mode.warmup_ms = 0;
+        config.mode.kill_limit = 1;
+        let (game_tx, _) = unbounded_channel();
+        GameArea::new(64, config, game_tx)
+    }
+
+    fn ended(responses: &mut UnboundedReceiver<GameResponse>) -> Option<Outcome> {
+        received(responses)
+            .into_iter()
+            .find_map(|response| match response {
+                GameResponse::RoundEnded(_, outcome) => Some(outcome),
+                _ => None,
+            })
+    }
+
+    #[tokio::test]
+    async fn survival_rounds_end_when_every_player_is_down() {
+        let mut area = mode_area(ModeKind::Survival);
+        let (_, object_id, mut responses) = join(&mut area, 1);
+
+        area.update_round();
+        area.update_round();
+        assert_eq!(area.round.state, RoundState::Playing);
+        area.update_round();
+        assert!(ended(&mut responses).is_none());
+
+        area.kill_player(object_id, None);
+        area.update_round();
+        assert!(matches!(ended(&mut responses), Some(Outcome::Survived(_))));
+        assert_eq!(area.round.state, RoundState::PostGame);
+    }
+
+    #[tokio::test]
+    async fn deathmatch_rounds_go_to_the_first_to_the_kill_limit() {
+        let mut area = mode_area(ModeKind::Deathmatch);
+        let (_, killer, mut responses) = join(&mut area, 1);
+        let (_, victim, _) = join(&mut area, 2);
+
+        area.update_round();
+        area.update_round();
+        assert_eq!(area.round.state, RoundState::Playing);
+
+        area.apply_damage(
+            victim,
+            Damage {
+                source: killer,
+                kind: DamageKind::Melee,
+                amount: 255,
+            },
+        );
+        area.update_round();
+        assert!(matches!(ended(&mut responses), Some(Outcome::Winner(winner)) if winner == killer));
+    }
+
+    /// Deathmatch where one player cuts another down from inside the tick hook
+    struct Ambush {
+        rules: Deathmatch,
+        killer: u32,
+        victim: u32,
+    }
+
+    impl GameMode for Ambush {
+        fn kind(&self) -> ModeKind {
+            ModeKind::Deathmatch
+        }
+
+        fn on_tick(&mut self, area: &mut GameArea) {
+            area.apply_damage(
+                self.victim,
+                Damage {
+                    source: self.killer,
+                    kind: DamageKind::Melee,
+                    amount: 255,
+                },
+            );
+        }
+
+        fn on_kill(&mut self, area: &mut GameArea, victim: u32, killer: u32) {
+            self.rules.on_kill(area, victim, killer);
+        }
+
+        fn check_victory(&self, area: &GameArea) -> Option<Outcome> {
+            self.rules.check_victory(area)
+        }
+    }
+
+    #[tokio::test]
+    async fn kills_made_by_mode_hooks_are_scored() {
+        let mut area = mode_area(ModeKind::Deathmatch);
+        let (_, killer, mut responses) = join(&mut area, 1);
+        let (_, victim, _) = join(&mut area, 2);
+        area.set_mode(Box::new(Ambush {
+            rules: Deathmatch::default(),
+            killer,
+            victim,
+        }));
+
+        area.update_round();
+        area.update_round();
+        assert_eq!(area.round.state, RoundState::Playing);
+
+        area.update_round();
+        assert!(!area.objects[&victim].alive);
+        assert!(matches!(ended(&mut responses), Some(Outcome::Winner(winner)) if winner == killer));
+    }
+
+    #[tokio::test]
+    async fn admins_can_kick_and_ban_spectators() {
+        let mut config = Config::default();
+        config.moderation.ban_list_path =
+            std::env::temp_dir().join(format!("spectator-bans-{}.json", std::process::id()));
+        let (game_tx, _) = unbounded_channel();
+        let mut area = GameArea::new(64, config, game_tx);
+        let (conn, mut responses) = unbounded_channel();
+        let client = Client {
+            client_id: 5,
+            addr: "10.0.0.5:3030".parse().unwrap(),
+        };
+        area.spectators.insert(
+            client.client_id,
+            Spectator {
+                client,
+                conn,
+                full_map: false,
+                following: None,
+            },
+        );
+
+        let reply = area.kick(client.client_id, None, true).await;
+        assert!(matches!(reply, AdminReply::Ok));
+        assert!(area.spectators.is_empty());
+        assert!(area.bans.is_banned(None, client.addr.ip()));
+        assert!(received(&mut responses).iter().any(
+            |response| matches!(response, GameResponse::Goodbye(Some(reason)) if reason == "Banned")
+        ));
+
+        let _ = std::fs::remove_file(&area.config.moderation.ban_list_path);
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::*;
+
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use crate::effects::EffectKind;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ItemKind {
+    HealthPack,
+    Ammo,
+    PowerUp,
+    Currency,
+}
+
+/// One kind of item that can lie around the world, `amount` is what a pickup is worth
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ItemDefinition {
+    pub name: String,
+    pub kind: ItemKind,
+    pub amount: u32,
+    /// Relative chance of this item being picked when the world is restocked
+    pub weight: u32,
+    /// Applied on pickup instead of going into the inventory
+    #[serde(default)]
+    pub effect: Option<EffectKind>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ItemConfig {
+    pub population: u32,
+    pub max_spawns_per_tick: usize,
+    pub pickup_radius: f32,
+    /// Most of each kind a player can carry, kinds not listed are unlimited
+    pub carry_limits: HashMap<ItemKind, u32>,
+    pub starting_inventory: HashMap<ItemKind, u32>,
+    pub definitions: Vec<ItemDefinition>,
+}
+
+impl Default for ItemConfig {
+    fn default() -> ItemConfig {
+        ItemConfig {
+            population: 40,
+            max_spawns_per_tick: 2,
+            pickup_radius: 3.0,
+            carry_limits: HashMap::from([(ItemKind::Ammo, 200), (ItemKind::PowerUp, 3)]),
+            starting_inventory: HashMap::from([(ItemKind::Ammo, 50)]),
+            definitions: vec![
+                ItemDefinition {
+                    name: "health_pack".to_string(),
+                    kind: ItemKind::HealthPack,
+                    amount: 25,
+                    weight: 3,
+                    effect: None,
+                },
+                ItemDefinition {
+                    name: "ammo_box".to_string(),
+                    kind: ItemKind::Ammo,
+                    amount: 20,
+                    weight: 4,
+                    effect: None,
+                },
+                ItemDefinition {
+                    name: "speed_boost".to_string(),
+                    kind: ItemKind::PowerUp,
+                    amount: 1,
+                    weight: 1,
+                    effect: Some(EffectKind::SpeedBoost),
+                },
+                ItemDefinition {
+                    name: "shield".to_string(),
+                    kind: ItemKind::PowerUp,
+                    amount: 1,
+                    weight: 1,
+                    effect: Some(EffectKind::Shield),
+                },
+                ItemDefinition {
+                    name: "damage_boost".to_string(),
+                    kind: ItemKind::PowerUp,
+                    amount: 1,
+                    weight: 1,
+                    effect: Some(EffectKind::DamageMultiplier),
+                },
+                ItemDefinition {
+                    name: "coin".to_string(),
+                    kind: ItemKind::Currency,
+                    amount: 1,
+                    weight: 5,
+                    effect: None,
+                },
+            ],
+        }
+    }
+}
+
+impl ItemConfig {
+    pub fn definition(&self, name: &str) -> Option<&ItemDefinition> {
+        self.definitions
+            .iter()
+            .find(|definition| definition.name == name)
+    }
+
+    /// Picks a definition at random, favoring the heavier ones
+    pub fn random_definition(&self) -> Option<&ItemDefinition> {
+        let total: u32 = self.definitions.iter().map(|d| d.weight).sum();
+        if total == 0 {
+            return None;
+        }
+
+        let mut roll = rand::thread_rng().gen_range(0..total);
+        for definition in &self.definitions {
+            if roll < definition.weight {
+                return Some(definition);
+            }
+            roll -= definition.weight;
+        }
+        None
+    }
+
+    pub fn carry_limit(&self, kind: ItemKind) -> u32 {
+        self.carry_limits.get(&kind).cloned().unwrap_or(u32::MAX)
+    }
+}
+
+/// An item lying in the world waiting to be picked up
+#[derive(Component, Clone, Debug)]
+pub struct Item {
+    pub name: String,
+}
+
+/// What a player is carrying
+#[derive(Component, Clone, Debug, Default, Serialize)]
+pub struct Inventory {
+    pub counts: HashMap<ItemKind, u32>,
+}
+
+impl Inventory {
+    pub fn new(counts: &HashMap<ItemKind, u32>) -> Inventory {
+        Inventory {
+            counts: counts.clone(),
+        }
+    }
+
+    /// Adds up to `limit` of a kind, returning how many actually fit
+    pub fn add(&mut self, kind: ItemKind, amount: u32, limit: u32) -> u32 {
+        let count = self.counts.entry(kind).or_insert(0);
+        let added = amount.min(limit.saturating_sub(*count));
+        *count += added;
+        added
+    }
+
+    /// Removes `amount` of a kind if there is that much to take
+    pub fn take(&mut self, kind: ItemKind, amount: u32) -> bool {
+        match self.counts.get_mut(&kind) {
+            Some(count) if *count >= amount => {
+                *count -= amount;
+                true
+            }
+            _ => amount == 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_stops_at_the_carry_limit() {
+        let mut inventory = Inventory::new(&HashMap::from([(ItemKind::Ammo, 190)]));
+
+        assert_eq!(inventory.add(ItemKind::Ammo, 20, 200), 10);
+        assert_eq!(inventory.counts[&ItemKind::Ammo], 200);
+        assert_eq!(inventory.add(ItemKind::Ammo, 20, 200), 0);
+        assert_eq!(inventory.add(ItemKind::Currency, 5, u32::MAX), 5);
+    }
+
+    #[test]
+    fn take_only_succeeds_with_enough_carried() {
+        let mut inventory = Inventory::new(&HashMap::from([(ItemKind::Ammo, 2)]));
+
+        assert!(inventory.take(ItemKind::Ammo, 2));
+        assert!(!inventory.take(ItemKind::Ammo, 1));
+        assert!(!inventory.take(ItemKind::PowerUp, 1));
+        assert!(inventory.take(ItemKind::PowerUp, 0));
+    }
+}
@@ -1,20 +1,22 @@
 // #![deny(warnings)]
 
-extern crate pretty_env_logger;
-
 #[macro_use]
 extern crate rmp_serde as rmps;
-extern crate log;
 
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 
 use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio::sync::oneshot;
 use tokio::time::{self, Duration};
 
 use futures_util::{SinkExt, StreamExt};
 
+use tracing::Instrument;
+use tracing_subscriber::EnvFilter;
+
 use warp::ws::{Message, WebSocket};
 use warp::Filter;
 
@@ -22,57 +24,131 @@ use rmps::Serializer;
 use serde::Serialize;
 
 mod actor;
+mod admin;
+mod api;
+mod behavior;
+mod chat;
+mod combat;
+mod config;
 mod data_structs;
+mod deathmatch;
+mod effects;
+mod flowfield;
+mod fsm;
 mod game;
+mod items;
+mod metrics;
+mod mode;
+mod moderation;
 mod net;
+mod pathfinding;
+mod persist;
+mod scoreboard;
+mod shutdown;
+mod snapshot;
+mod spawner;
+mod spectator;
+mod survival;
+mod teams;
 mod terrain;
 
+use admin::admin_route;
+use api::api_routes;
+use chat::ChatChannel;
+use config::{watch_config, Config};
 use game::{Client, GameArea, GameMessage, GameResponse};
+use metrics::{metrics_route, METRICS};
+use shutdown::shutdown_signal;
+use snapshot::load_snapshot;
 
 const AREA_SIZE: u32 = 1000;
+const DEFAULT_CONFIG_PATH: &str = "config.json";
 
 #[derive(Debug, Deserialize)]
 pub enum ClientMessage {
     Hello(String),
+    Spectate(bool),
+    Follow(Option<u32>),
     Ping(u64),
     Goodbye(),
     Move(f32, f32, f32),
+    Fire(f32, f32, f32),
+    RequestRespawn(),
+    Chat { channel: ChatChannel, text: String },
+}
+
+impl ClientMessage {
+    /// Variant name, used to label metrics
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClientMessage::Hello(..) => "Hello",
+            ClientMessage::Spectate(..) => "Spectate",
+            ClientMessage::Follow(..) => "Follow",
+            ClientMessage::Ping(..) => "Ping",
+            ClientMessage::Goodbye() => "Goodbye",
+            ClientMessage::Move(..) => "Move",
+            ClientMessage::Fire(..) => "Fire",
+            ClientMessage::RequestRespawn() => "RequestRespawn",
+            ClientMessage::Chat { .. } => "Chat",
+        }
+    }
 }
 
+#[tracing::instrument(
+    name = "connection",
+    skip_all,
+    fields(client_id = client.client_id, addr = %client.addr)
+)]
 async fn user_connected(
     client: Client,
     websocket: WebSocket,
     game_conn: UnboundedSender<GameMessage>,
 ) {
+    METRICS.connections.fetch_add(1, Ordering::Relaxed);
     let (mut websocket_tx, mut websocket_rx) = websocket.split();
     let (client_tx, mut client_rx) = unbounded_channel::<GameResponse>();
 
     // one thread reading from websocket_rx, decoding messages, and pumping to game_conn
     // one thread reading from client_rx and, encoding, and pumping to websocket_tx
     // messages over game conn send client_tx for responses
-    tokio::spawn(async move {
-        // FIXME this could use some corking
-        while let Some(msg) = client_rx.recv().await {
-            let mut buf = Vec::new();
-            let mut serializer = Serializer::new(&mut buf);
-            let result = msg.serialize(&mut serializer);
-            if let Err(e) = result {
-                log::error!("websocket serialize error {:?}: {}", client, e);
-                break;
-            }
-            let result = websocket_tx.send(Message::binary(buf)).await;
-            if let Err(e) = result {
-                log::error!("websocket write error {:?}: {}", client, e);
-                break;
+    tokio::spawn(
+        async move {
+            // FIXME this could use some corking
+            while let Some(msg) = client_rx.recv().await {
+                let closing = matches!(msg, GameResponse::Goodbye(_));
+                let name = msg.name();
+                let mut buf = Vec::new();
+                let mut serializer = Serializer::new(&mut buf);
+                let result = msg.serialize(&mut serializer);
+                if let Err(e) = result {
+                    tracing::error!("websocket serialize error {:?}: {}", client, e);
+                    break;
+                }
+                let len = buf.len();
+                let result = websocket_tx.send(Message::binary(buf)).await;
+                if let Err(e) = result {
+                    tracing::error!("websocket write error {:?}: {}", client, e);
+                    break;
+                }
+                METRICS.message_out(name, client.client_id, len);
+                // the game is done with this client, e.g. it was kicked
+                if closing {
+                    if let Err(e) = websocket_tx.close().await {
+                        tracing::error!("websocket close error {:?}: {}", client, e);
+                    }
+                    break;
+                }
             }
+            METRICS.forget_client(client.client_id);
         }
-    });
+        .in_current_span(),
+    );
 
     while let Some(result) = websocket_rx.next().await {
         let encoded_msg = match result {
             Ok(msg) => msg,
             Err(e) => {
-                log::error!("websocket read error {:?}: {}", client, e);
+                tracing::error!("websocket read error {:?}: {}", client, e);
                 break;
             }
         };
@@ -86,44 +162,85 @@ async fn user_connected(
         let msg: ClientMessage = match rmp_serde::from_slice(encoded_msg.as_bytes()) {
             Ok(msg) => msg,
             Err(e) => {
-                log::error!("websocket deserialize error {:?}: {}", client, e);
+                tracing::error!("websocket deserialize error {:?}: {}", client, e);
                 break;
             }
         };
 
+        METRICS.message_in(msg.name());
+
         let game_msg = match msg {
             ClientMessage::Hello(username) => {
                 GameMessage::Hello(client, client_tx.clone(), username)
             }
+            ClientMessage::Spectate(full_map) => {
+                GameMessage::Spectate(client, client_tx.clone(), full_map)
+            }
+            ClientMessage::Follow(object_id) => GameMessage::Follow(client, object_id),
             ClientMessage::Ping(timestamp) => GameMessage::Ping(client, timestamp),
             ClientMessage::Goodbye() => GameMessage::Goodbye(client),
             ClientMessage::Move(x, y, z) => GameMessage::Move(client, x, y, z),
+            ClientMessage::Fire(x, y, z) => GameMessage::Fire(client, x, y, z),
+            ClientMessage::RequestRespawn() => GameMessage::RequestRespawn(client),
+            ClientMessage::Chat { channel, text } => GameMessage::Chat(client, channel, text),
         };
 
         let result = game_conn.send(game_msg);
         if let Err(e) = result {
-            log::error!("error sending hello {:?}: {}", client, e);
+            tracing::error!("error sending hello {:?}: {}", client, e);
         }
     }
 
-    log::info!("client shutdown");
+    tracing::info!("client shutdown");
     let result = game_conn.send(GameMessage::Goodbye(client));
     if let Err(e) = result {
-        log::error!("error sending hello {:?}: {}", client, e);
+        tracing::error!("error sending hello {:?}: {}", client, e);
+    }
+    METRICS.connections.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Logs to stderr, filtered per module with `RUST_LOG` (e.g. `info,crashtv::game=debug`)
+/// and as JSON lines when `CRASHTV_LOG_FORMAT=json`
+fn init_logging() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let json = std::env::var("CRASHTV_LOG_FORMAT").is_ok_and(|format| format == "json");
+
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    if json {
+        subscriber.json().init();
+    } else {
+        subscriber.init();
     }
 }
 
 #[tokio::main]
 async fn main() {
-    pretty_env_logger::init();
+    init_logging();
+
+    let config_path = PathBuf::from(
+        std::env::var("CRASHTV_CONFIG").unwrap_or_else(|_| DEFAULT_CONFIG_PATH.to_string()),
+    );
+    let config = Config::load_or_default(&config_path);
+    let shutdown_config = config.shutdown.clone();
 
     let (game_tx, game_rx) = unbounded_channel::<GameMessage>();
 
+    tokio::spawn(watch_config(config_path, game_tx.clone()));
+
     let tx = game_tx.clone();
-    tokio::spawn(async move {
-        let mut area = GameArea::new(AREA_SIZE, tx.clone());
-        // area.populate(100, 100);
-        log::info!("game server running");
+    let game_handle = tokio::spawn(async move {
+        let snapshot = if config.snapshot.restore_on_start {
+            load_snapshot(&config.snapshot.path)
+        } else {
+            None
+        };
+        let mut area = match snapshot {
+            Some(snapshot) => GameArea::restore(snapshot, config, tx.clone()),
+            None => GameArea::new(AREA_SIZE, config, tx.clone()),
+        };
+        tracing::info!("game server running");
         area.process(game_rx).await
     });
 
@@ -134,12 +251,17 @@ async fn main() {
 
         loop {
             let now = interval.tick().await;
-            if let Err(e) = tx.send(GameMessage::Tick(now)) {
-                log::error!("error sending tick: {}", e);
+            // the game has stopped, nothing left to tick
+            if tx.send(GameMessage::Tick(now)).is_err() {
+                break;
             }
         }
     });
 
+    let api = api_routes(game_tx.clone());
+    let admin = admin_route(std::env::var("CRASHTV_ADMIN_SECRET").ok(), game_tx.clone());
+
+    let shutdown_tx = game_tx.clone();
     let next_client_id = Arc::new(AtomicU32::new(1));
 
     let route = warp::path("ws")
@@ -157,7 +279,7 @@ async fn main() {
                     addr: addr.unwrap(),
                 };
 
-                log::info!("{:?} connected", client);
+                tracing::info!("{:?} connected", client);
 
                 ws.with_compression()
                     .on_upgrade(move |websocket| async move {
@@ -166,5 +288,33 @@ async fn main() {
             },
         );
 
-    warp::serve(route).run(([127, 0, 0, 1], 3030)).await;
+    let (_, server) = warp::serve(route.or(api).or(metrics_route()).or(admin))
+        .bind_with_graceful_shutdown(([127, 0, 0, 1], 3030), shutdown_signal());
+    server.await;
+
+    tracing::info!("no longer accepting connections");
+    let deadline = Duration::from_millis(shutdown_config.deadline_ms);
+    let drained = time::timeout(deadline, drain(shutdown_tx, shutdown_config.reason)).await;
+    if drained.is_err() {
+        tracing::warn!("shutdown deadline of {:?} passed, exiting anyway", deadline);
+        game_handle.abort();
+    }
+}
+
+/// Has the game send everyone away, then waits for their connections to close
+async fn drain(game_tx: UnboundedSender<GameMessage>, reason: String) {
+    let (done_tx, done_rx) = oneshot::channel();
+    if let Err(e) = game_tx.send(GameMessage::Shutdown(reason, done_tx)) {
+        tracing::error!("error sending shutdown: {}", e);
+        return;
+    }
+    if done_rx.await.is_err() {
+        tracing::error!("game stopped before finishing shutdown");
+    }
+
+    let mut interval = time::interval(Duration::from_millis(50));
+    while METRICS.connections.load(Ordering::Relaxed) > 0 {
+        interval.tick().await;
+    }
+    tracing::info!("shutdown complete");
 }
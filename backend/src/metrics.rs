@@ -0,0 +1,267 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use tokio::time::Duration;
+
+use warp::Filter;
+
+const DURATION_BUCKETS: [f64; 10] = [
+    0.0005, 0.001, 0.002, 0.004, 0.008, 0.016, 0.032, 0.064, 0.128, 0.256,
+];
+
+/// Durations counted into cumulative buckets the way Prometheus expects them
+pub struct Histogram {
+    buckets: [AtomicU64; DURATION_BUCKETS.len()],
+    sum_micros: AtomicU64,
+    count: AtomicU64,
+}
+
+impl Histogram {
+    const fn new() -> Histogram {
+        Histogram {
+            buckets: [const { AtomicU64::new(0) }; DURATION_BUCKETS.len()],
+            sum_micros: AtomicU64::new(0),
+            count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        for (bound, bucket) in DURATION_BUCKETS.iter().zip(self.buckets.iter()) {
+            if seconds <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (bound, bucket) in DURATION_BUCKETS.iter().zip(self.buckets.iter()) {
+            let _ = writeln!(
+                out,
+                "{}_bucket{{le=\"{}\"}} {}",
+                name,
+                bound,
+                bucket.load(Ordering::Relaxed)
+            );
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1_000_000.0;
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, count);
+        let _ = writeln!(out, "{}_sum {}", name, sum);
+        let _ = writeln!(out, "{}_count {}", name, count);
+    }
+}
+
+fn render_gauge(out: &mut String, name: &str, help: &str, value: &AtomicU64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+}
+
+fn render_counters<K: std::fmt::Display>(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    counts: &Mutex<BTreeMap<K, u64>>,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    if let Ok(counts) = counts.lock() {
+        for (key, count) in counts.iter() {
+            let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", name, label, key, count);
+        }
+    }
+}
+
+fn count<K: Ord>(counts: &Mutex<BTreeMap<K, u64>>, key: K, amount: u64) {
+    if let Ok(mut counts) = counts.lock() {
+        *counts.entry(key).or_insert(0) += amount;
+    }
+}
+
+/// Server health numbers, updated from wherever they happen and read by `/metrics`
+pub struct Metrics {
+    pub tick_duration: Histogram,
+    pub scan_latency: Histogram,
+    pub tps: AtomicU64,
+    pub queue_depth: AtomicU64,
+    pub connections: AtomicU64,
+    pub players: AtomicU64,
+    pub spectators: AtomicU64,
+    pub actors: AtomicU64,
+    messages_in: Mutex<BTreeMap<&'static str, u64>>,
+    messages_out: Mutex<BTreeMap<&'static str, u64>>,
+    bytes_sent: Mutex<BTreeMap<u32, u64>>,
+}
+
+pub static METRICS: Metrics = Metrics::new();
+
+impl Metrics {
+    const fn new() -> Metrics {
+        Metrics {
+            tick_duration: Histogram::new(),
+            scan_latency: Histogram::new(),
+            tps: AtomicU64::new(0),
+            queue_depth: AtomicU64::new(0),
+            connections: AtomicU64::new(0),
+            players: AtomicU64::new(0),
+            spectators: AtomicU64::new(0),
+            actors: AtomicU64::new(0),
+            messages_in: Mutex::new(BTreeMap::new()),
+            messages_out: Mutex::new(BTreeMap::new()),
+            bytes_sent: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn message_in(&self, kind: &'static str) {
+        count(&self.messages_in, kind, 1);
+    }
+
+    pub fn message_out(&self, kind: &'static str, client_id: u32, bytes: usize) {
+        count(&self.messages_out, kind, 1);
+        count(&self.bytes_sent, client_id, bytes as u64);
+    }
+
+    /// Drops a disconnected client's byte count so the label set doesn't grow forever
+    pub fn forget_client(&self, client_id: u32) {
+        if let Ok(mut bytes_sent) = self.bytes_sent.lock() {
+            bytes_sent.remove(&client_id);
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.tick_duration.render(
+            &mut out,
+            "crashtv_tick_duration_seconds",
+            "Time spent running each game tick",
+        );
+        self.scan_latency.render(
+            &mut out,
+            "crashtv_scan_latency_seconds",
+            "Time from an actor asking for a scan to getting its perception",
+        );
+        render_gauge(
+            &mut out,
+            "crashtv_tps",
+            "Ticks achieved per second",
+            &self.tps,
+        );
+        render_gauge(
+            &mut out,
+            "crashtv_game_queue_depth",
+            "Messages waiting for the game task",
+            &self.queue_depth,
+        );
+        render_gauge(
+            &mut out,
+            "crashtv_connections",
+            "Open websocket connections",
+            &self.connections,
+        );
+        render_gauge(
+            &mut out,
+            "crashtv_players",
+            "Connected players",
+            &self.players,
+        );
+        render_gauge(
+            &mut out,
+            "crashtv_spectators",
+            "Connected spectators",
+            &self.spectators,
+        );
+        render_gauge(
+            &mut out,
+            "crashtv_actors",
+            "Actors in the world",
+            &self.actors,
+        );
+        render_counters(
+            &mut out,
+            "crashtv_messages_in_total",
+            "Messages received from clients",
+            "type",
+            &self.messages_in,
+        );
+        render_counters(
+            &mut out,
+            "crashtv_messages_out_total",
+            "Messages sent to clients",
+            "type",
+            &self.messages_out,
+        );
+        render_counters(
+            &mut out,
+            "crashtv_bytes_sent_total",
+            "Bytes sent to each connected client",
+            "client_id",
+            &self.bytes_sent,
+        );
+        out
+    }
+}
+
+/// GET /metrics in the Prometheus text format
+pub fn metrics_route() -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
+    warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .map(|| {
+            warp::reply::with_header(
+                METRICS.render(),
+                "content-type",
+                "text/plain; version=0.0.4",
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let histogram = Histogram::new();
+        histogram.observe(Duration::from_micros(300));
+        histogram.observe(Duration::from_millis(3));
+        histogram.observe(Duration::from_secs(1));
+
+        let mut out = String::new();
+        histogram.render(&mut out, "tick_seconds", "Tick time");
+        let lines: Vec<&str> = out.lines().collect();
+
+        assert_eq!(lines[0], "# HELP tick_seconds Tick time");
+        assert_eq!(lines[1], "# TYPE tick_seconds histogram");
+        assert_eq!(lines[2], "tick_seconds_bucket{le=\"0.0005\"} 1");
+        assert_eq!(lines[4], "tick_seconds_bucket{le=\"0.002\"} 1");
+        assert_eq!(lines[5], "tick_seconds_bucket{le=\"0.004\"} 2");
+        assert_eq!(lines[11], "tick_seconds_bucket{le=\"0.256\"} 2");
+        assert_eq!(lines[12], "tick_seconds_bucket{le=\"+Inf\"} 3");
+        assert_eq!(lines[13], "tick_seconds_sum 1.0033");
+        assert_eq!(lines[14], "tick_seconds_count 3");
+    }
+
+    #[test]
+    fn counters_are_labelled_per_key() {
+        let counts = Mutex::new(BTreeMap::new());
+        count(&counts, "Ping", 1);
+        count(&counts, "Hello", 1);
+        count(&counts, "Ping", 2);
+
+        let mut out = String::new();
+        render_counters(&mut out, "messages_total", "Messages", "type", &counts);
+        assert!(out.contains("# TYPE messages_total counter\n"));
+        assert!(
+            out.ends_with("messages_total{type=\"Hello\"} 1\nmessages_total{type=\"Ping\"} 3\n")
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use tokio::time::{Duration, Instant};
+
+use crate::deathmatch::{Deathmatch, TeamDeathmatch};
+use crate::game::GameArea;
+use crate::survival::{RoundStats, SurvivalMode};
+use crate::teams::TeamConfig;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModeKind {
+    FreeRoam,
+    Survival,
+    Deathmatch,
+    TeamDeathmatch,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum RoundState {
+    Lobby,
+    Warmup,
+    Playing,
+    PostGame,
+}
+
+/// How a round was won, sent to everyone when it ends
+#[derive(Clone, Debug, Serialize)]
+pub enum Outcome {
+    Winner(u32),
+    WinningTeam(u32),
+    Survived(RoundStats),
+    Draw,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModeConfig {
+    pub mode: ModeKind,
+    pub min_players: usize,
+    pub warmup_ms: u64,
+    pub post_game_ms: u64,
+    /// Rounds that run this long end in favor of whoever is ahead, zero for no limit
+    pub time_limit_ms: u64,
+    pub kill_limit: u32,
+}
+
+impl Default for ModeConfig {
+    fn default() -> ModeConfig {
+        ModeConfig {
+            mode: ModeKind::FreeRoam,
+            min_players: 1,
+            warmup_ms: 10000,
+            post_game_ms: 10000,
+            time_limit_ms: 600000,
+            kill_limit: 20,
+        }
+    }
+}
+
+/// Rules for a kind of match, driven by the round state machine in `GameArea`
+pub trait GameMode: Send {
+    fn kind(&self) -> ModeKind;
+
+    /// Whether the mode has rounds at all, free roam just plays forever
+    fn has_rounds(&self) -> bool {
+        true
+    }
+
+    /// Teams players are split into on joining, zero for every player for themselves
+    fn team_count(&self, _config: &TeamConfig) -> u32 {
+        0
+    }
+
+    fn allows_respawn(&self, _state: RoundState) -> bool {
+        true
+    }
+
+    fn on_round_start(&mut self, _area: &mut GameArea) {}
+
+    fn on_round_end(&mut self, _area: &mut GameArea) {}
+
+    fn on_player_join(&mut self, _area: &mut GameArea, _object_id: u32) {}
+
+    fn on_player_leave(&mut self, _area: &mut GameArea, _object_id: u32) {}
+
+    fn on_tick(&mut self, _area: &mut GameArea) {}
+
+    fn on_kill(&mut self, _area: &mut GameArea, _victim: u32, _killer: u32) {}
+
+    /// Called each tick while playing, a result ends the round
+    fn check_victory(&self, area: &GameArea) -> Option<Outcome>;
+}
+
+pub fn new_mode(kind: ModeKind) -> Box<dyn GameMode> {
+    match kind {
+        ModeKind::FreeRoam => Box::new(FreeRoam),
+        ModeKind::Survival => Box::new(SurvivalMode::default()),
+        ModeKind::Deathmatch => Box::new(Deathmatch::default()),
+        ModeKind::TeamDeathmatch => Box::new(TeamDeathmatch::default()),
+    }
+}
+
+/// Wander around and fight whatever turns up, forever
+pub struct FreeRoam;
+
+impl GameMode for FreeRoam {
+    fn kind(&self) -> ModeKind {
+        ModeKind::FreeRoam
+    }
+
+    fn has_rounds(&self) -> bool {
+        false
+    }
+
+    fn check_victory(&self, _area: &GameArea) -> Option<Outcome> {
+        None
+    }
+}
+
+/// Where the current round is and how long it has been there
+pub struct Round {
+    pub number: u32,
+    pub state: RoundState,
+    pub entered_at: Instant,
+}
+
+impl Round {
+    pub fn new() -> Round {
+        Round {
+            number: 0,
+            state: RoundState::Lobby,
+            entered_at: Instant::now(),
+        }
+    }
+
+    pub fn enter(&mut self, state: RoundState) {
+        if state == RoundState::Playing {
+            self.number += 1;
+        }
+        self.state = state;
+        self.entered_at = Instant::now();
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.entered_at.elapsed()
+    }
+
+    /// Time left before the state moves on by itself, zero if it won't
+    pub fn remaining_ms(&self, config: &ModeConfig) -> u64 {
+        let limit = match self.state {
+            RoundState::Lobby => 0,
+            RoundState::Warmup => config.warmup_ms,
+            RoundState::Playing => config.time_limit_ms,
+            RoundState::PostGame => config.post_game_ms,
+        };
+        (limit as u128).saturating_sub(self.elapsed().as_millis()) as u64
+    }
+}
@@ -0,0 +1,214 @@
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::persist::{load_json, save_json_atomic};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModerationConfig {
+    pub min_username_length: usize,
+    pub max_username_length: usize,
+    /// Names nobody may take, compared ignoring case
+    pub reserved_usernames: Vec<String>,
+    pub ban_list_path: PathBuf,
+}
+
+impl Default for ModerationConfig {
+    fn default() -> ModerationConfig {
+        ModerationConfig {
+            min_username_length: 3,
+            max_username_length: 16,
+            reserved_usernames: vec![
+                "admin".to_string(),
+                "moderator".to_string(),
+                "server".to_string(),
+                "system".to_string(),
+            ],
+            ban_list_path: PathBuf::from("bans.json"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum UsernameError {
+    TooShort(usize),
+    TooLong(usize),
+    /// Only ASCII letters, digits, `_` and `-` are allowed
+    InvalidCharacters,
+    Reserved,
+}
+
+/// Why a connection's hello was turned away
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub enum HelloError {
+    AlreadyConnected,
+    InvalidUsername(UsernameError),
+    UsernameTaken,
+    Banned,
+    TooManySpectators,
+}
+
+pub fn validate_username(config: &ModerationConfig, username: &str) -> Result<(), UsernameError> {
+    let length = username.chars().count();
+    if length < config.min_username_length {
+        return Err(UsernameError::TooShort(config.min_username_length));
+    }
+    if length > config.max_username_length {
+        return Err(UsernameError::TooLong(config.max_username_length));
+    }
+    if !username
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(UsernameError::InvalidCharacters);
+    }
+    if config
+        .reserved_usernames
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(username))
+    {
+        return Err(UsernameError::Reserved);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Bans {
+    usernames: HashSet<String>,
+    addrs: HashSet<IpAddr>,
+}
+
+/// Usernames and addresses refused at the handshake, saved whenever it changes
+#[derive(Debug)]
+pub struct BanList {
+    path: PathBuf,
+    bans: Bans,
+}
+
+impl BanList {
+    pub fn new(config: &ModerationConfig) -> BanList {
+        BanList {
+            path: config.ban_list_path.clone(),
+            bans: load_json(&config.ban_list_path, "ban list").unwrap_or_default(),
+        }
+    }
+
+    /// Switches to another ban list file if the path changed
+    pub fn set_config(&mut self, config: &ModerationConfig) {
+        if config.ban_list_path != self.path {
+            self.path = config.ban_list_path.clone();
+            self.bans = load_json(&self.path, "ban list").unwrap_or_default();
+        }
+    }
+
+    pub fn ban(&mut self, username: Option<&str>, addr: IpAddr) {
+        if let Some(username) = username {
+            self.bans.usernames.insert(username.to_lowercase());
+        }
+        self.bans.addrs.insert(addr);
+        self.save();
+    }
+
+    pub fn is_banned(&self, username: Option<&str>, addr: IpAddr) -> bool {
+        self.bans.addrs.contains(&addr)
+            || username
+                .is_some_and(|username| self.bans.usernames.contains(&username.to_lowercase()))
+    }
+
+    fn save(&self) {
+        if let Err(e) = save_json_atomic(&self.path, &self.bans) {
+            tracing::error!("error saving ban list {:?}: {}", self.path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_plain_usernames() {
+        let config = ModerationConfig::default();
+
+        assert_eq!(validate_username(&config, "dino_42"), Ok(()));
+        assert_eq!(validate_username(&config, "a-b"), Ok(()));
+    }
+
+    #[test]
+    fn rejects_usernames_outside_the_length_limits() {
+        let config = ModerationConfig::default();
+
+        assert_eq!(
+            validate_username(&config, "ab"),
+            Err(UsernameError::TooShort(3))
+        );
+        assert_eq!(
+            validate_username(&config, "abcdefghijklmnopq"),
+            Err(UsernameError::TooLong(16))
+        );
+    }
+
+    #[test]
+    fn counts_length_in_characters_not_bytes() {
+        let config = ModerationConfig::default();
+
+        assert_eq!(
+            validate_username(&config, "éé"),
+            Err(UsernameError::TooShort(3))
+        );
+        assert_eq!(
+            validate_username(&config, "ééé"),
+            Err(UsernameError::InvalidCharacters)
+        );
+    }
+
+    #[test]
+    fn rejects_spaces_and_symbols() {
+        let config = ModerationConfig::default();
+
+        assert_eq!(
+            validate_username(&config, "dino 42"),
+            Err(UsernameError::InvalidCharacters)
+        );
+        assert_eq!(
+            validate_username(&config, "dino!"),
+            Err(UsernameError::InvalidCharacters)
+        );
+    }
+
+    #[test]
+    fn rejects_reserved_usernames_ignoring_case() {
+        let config = ModerationConfig::default();
+
+        assert_eq!(
+            validate_username(&config, "Admin"),
+            Err(UsernameError::Reserved)
+        );
+    }
+
+    #[test]
+    fn bans_cover_the_username_in_any_case_and_the_address() {
+        let path = std::env::temp_dir().join(format!("bans-{}.json", std::process::id()));
+        let config = ModerationConfig {
+            ban_list_path: path.clone(),
+            ..ModerationConfig::default()
+        };
+        let mut bans = BanList::new(&config);
+        let addr: IpAddr = "10.0.0.1".parse().unwrap();
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        bans.ban(Some("Griefer"), addr);
+
+        assert!(bans.is_banned(Some("gRIEFER"), other));
+        assert!(bans.is_banned(None, addr));
+        assert!(!bans.is_banned(Some("someone"), other));
+
+        let reloaded = BanList::new(&config);
+        assert!(reloaded.is_banned(Some("griefer"), other));
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,456 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use nalgebra::Vector3;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::{Duration, Instant};
+
+use crate::game::FrozenGameObject;
+use crate::terrain::{Terrain, TerrainType};
+
+const SQRT_2: f32 = std::f32::consts::SQRT_2;
+
+const REPATH_DISTANCE: f32 = 20.0;
+const REPATH_INTERVAL: Duration = Duration::from_secs(2);
+const WAYPOINT_REACHED: f32 = 4.0;
+
+pub type Path = Arc<Vec<Vector3<f32>>>;
+
+/// Movement cost of crossing one unit of a biome, `None` where actors can't go
+pub fn default_movement_cost(terrain_type: TerrainType) -> Option<f32> {
+    match terrain_type {
+        TerrainType::Ocean => None,
+        TerrainType::Grassland => Some(1.0),
+        TerrainType::Beach
+        | TerrainType::Shrubland
+        | TerrainType::SubtropicalDesert
+        | TerrainType::TemperateDesert => Some(1.5),
+        TerrainType::Bare
+        | TerrainType::Scorched
+        | TerrainType::Taiga
+        | TerrainType::TemperateDeciduousForest
+        | TerrainType::TropicalSeasonalForest
+        | TerrainType::Tundra => Some(2.0),
+        TerrainType::TemperateRainForest | TerrainType::TropicalRainForest => Some(2.5),
+        TerrainType::Snow => Some(3.0),
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PathfindingConfig {
+    pub cell_size: u32,
+    pub max_slope: f32,
+    /// Cells a single search may expand before giving up
+    pub max_expansions: usize,
+    pub cache_size: usize,
+    /// Per-biome overrides of `default_movement_cost`, zero or less is impassable
+    pub costs: HashMap<TerrainType, f32>,
+}
+
+impl Default for PathfindingConfig {
+    fn default() -> PathfindingConfig {
+        PathfindingConfig {
+            cell_size: 5,
+            max_slope: 0.15,
+            max_expansions: 20000,
+            cache_size: 1024,
+            costs: HashMap::new(),
+        }
+    }
+}
+
+impl PathfindingConfig {
+    pub fn movement_cost(&self, terrain_type: TerrainType) -> Option<f32> {
+        match self.costs.get(&terrain_type) {
+            Some(cost) if *cost > 0.0 => Some(*cost),
+            Some(_) => None,
+            None => default_movement_cost(terrain_type),
+        }
+    }
+}
+
+pub type Cell = (i32, i32);
+
+#[derive(Copy, Clone, PartialEq)]
+pub struct Node {
+    pub estimate: f32,
+    pub cell: Cell,
+}
+
+impl Eq for Node {}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // min-heap on the estimated total cost
+        other
+            .estimate
+            .partial_cmp(&self.estimate)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Searches already made, oldest evicted first
+#[derive(Default)]
+struct PathCache {
+    paths: HashMap<(Cell, Cell), Option<Path>>,
+    order: VecDeque<(Cell, Cell)>,
+}
+
+/// Coarse navigation grid sampled from the terrain, with A* search over it.
+/// Shared behind an `Arc` so searches can run off the game task.
+pub struct Pathfinder {
+    pub cell_size: u32,
+    pub width: i32,
+    costs: Vec<Option<f32>>,
+    elevations: Vec<f32>,
+    min_cost: f32,
+    max_slope: f32,
+    max_expansions: usize,
+    cache_size: usize,
+    cache: Mutex<PathCache>,
+}
+
+impl Pathfinder {
+    pub fn new(terrain: &Terrain, config: &PathfindingConfig) -> Pathfinder {
+        let cell_size = config.cell_size.max(1);
+        let width = (terrain.size / cell_size).max(1) as i32;
+
+        let mut costs = Vec::with_capacity((width * width) as usize);
+        let mut elevations = Vec::with_capacity((width * width) as usize);
+        for cz in 0..width {
+            for cx in 0..width {
+                let x = cx as u32 * cell_size + cell_size / 2;
+                let z = cz as u32 * cell_size + cell_size / 2;
+                costs.push(config.movement_cost(terrain.get_terrain_type(x, z)));
+                elevations.push(terrain.get_elevation(x, z));
+            }
+        }
+
+        let min_cost = costs
+            .iter()
+            .flatten()
+            .cloned()
+            .fold(f32::INFINITY, f32::min);
+
+        Pathfinder {
+            cell_size,
+            width,
+            costs,
+            elevations,
+            min_cost: if min_cost.is_finite() { min_cost } else { 1.0 },
+            max_slope: config.max_slope,
+            max_expansions: config.max_expansions,
+            cache_size: config.cache_size,
+            cache: Mutex::new(PathCache::default()),
+        }
+    }
+
+    pub fn index(&self, cell: Cell) -> Option<usize> {
+        let (cx, cz) = cell;
+        if cx < 0 || cz < 0 || cx >= self.width || cz >= self.width {
+            return None;
+        }
+        Some((cz * self.width + cx) as usize)
+    }
+
+    pub fn cell(&self, position: &Vector3<f32>) -> Cell {
+        let size = self.cell_size as f32;
+        let max = self.width - 1;
+        (
+            ((position.x / size) as i32).clamp(0, max),
+            ((position.z / size) as i32).clamp(0, max),
+        )
+    }
+
+    pub fn center(&self, cell: Cell) -> Vector3<f32> {
+        let size = self.cell_size as f32;
+        Vector3::new(
+            (cell.0 as f32 + 0.5) * size,
+            0.0,
+            (cell.1 as f32 + 0.5) * size,
+        )
+    }
+
+    pub fn cost(&self, cell: Cell) -> Option<f32> {
+        self.index(cell).and_then(|idx| self.costs[idx])
+    }
+
+    pub fn is_walkable(&self, position: &Vector3<f32>) -> bool {
+        self.cost(self.cell(position)).is_some()
+    }
+
+    /// Whether the ground rises or drops too sharply to cross between two points
+    pub fn is_cliff(&self, from: &Vector3<f32>, to: &Vector3<f32>) -> bool {
+        let from_idx = self.index(self.cell(from));
+        let to_idx = self.index(self.cell(to));
+        match (from_idx, to_idx) {
+            (Some(from_idx), Some(to_idx)) => {
+                (self.elevations[to_idx] - self.elevations[from_idx]).abs() > self.max_slope
+            }
+            _ => false,
+        }
+    }
+
+    /// Cost of stepping between two adjacent cells, `None` if the step is blocked
+    pub fn step_cost(&self, from: Cell, to: Cell) -> Option<f32> {
+        let to_cost = self.cost(to)?;
+        let from_idx = self.index(from)?;
+        let to_idx = self.index(to)?;
+
+        let slope = (self.elevations[to_idx] - self.elevations[from_idx]).abs();
+        if slope > self.max_slope {
+            return None;
+        }
+
+        // starting from an impassable cell is allowed so stranded actors can get out
+        let from_cost = self.costs[from_idx].unwrap_or(to_cost);
+        let distance = if from.0 != to.0 && from.1 != to.1 {
+            SQRT_2
+        } else {
+            1.0
+        };
+
+        Some(distance * (from_cost + to_cost) / 2.0)
+    }
+
+    pub fn neighbors(&self, cell: Cell) -> impl Iterator<Item = Cell> + '_ {
+        let (cx, cz) = cell;
+        (-1..=1)
+            .flat_map(move |dz| (-1..=1).map(move |dx| (cx + dx, cz + dz)))
+            .filter(move |neighbor| *neighbor != cell && self.index(*neighbor).is_some())
+    }
+
+    fn heuristic(&self, a: Cell, b: Cell) -> f32 {
+        let dx = (a.0 - b.0).abs() as f32;
+        let dz = (a.1 - b.1).abs() as f32;
+        let octile = dx.max(dz) + (SQRT_2 - 1.0) * dx.min(dz);
+        octile * self.min_cost
+    }
+
+    fn search(&self, start: Cell, goal: Cell) -> Option<Vec<Vector3<f32>>> {
+        self.cost(goal)?;
+
+        let mut open = BinaryHeap::new();
+        let mut came_from: HashMap<Cell, Cell> = HashMap::new();
+        let mut best: HashMap<Cell, f32> = HashMap::new();
+
+        best.insert(start, 0.0);
+        open.push(Node {
+            estimate: self.heuristic(start, goal),
+            cell: start,
+        });
+
+        let mut expansions = 0;
+        while let Some(Node { cell, .. }) = open.pop() {
+            if cell == goal {
+                let mut path = vec![self.center(cell)];
+                let mut current = cell;
+                while let Some(previous) = came_from.get(&current) {
+                    current = *previous;
+                    path.push(self.center(current));
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            expansions += 1;
+            if expansions > self.max_expansions {
+                tracing::debug!("path search from {:?} to {:?} gave up", start, goal);
+                return None;
+            }
+
+            let cost_so_far = best[&cell];
+            for neighbor in self.neighbors(cell) {
+                let step = match self.step_cost(cell, neighbor) {
+                    Some(step) => step,
+                    None => continue,
+                };
+
+                let cost = cost_so_far + step;
+                if best.get(&neighbor).is_none_or(|known| cost < *known) {
+                    best.insert(neighbor, cost);
+                    came_from.insert(neighbor, cell);
+                    open.push(Node {
+                        estimate: cost + self.heuristic(neighbor, goal),
+                        cell: neighbor,
+                    });
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Finds a walkable path between two world positions, reusing cached searches.
+    /// A search can take a while, so call this from a blocking task.
+    pub fn find_path(&self, from: &Vector3<f32>, to: &Vector3<f32>) -> Option<Path> {
+        let key = (self.cell(from), self.cell(to));
+        if let Ok(cache) = self.cache.lock() {
+            if let Some(path) = cache.paths.get(&key) {
+                return path.clone();
+            }
+        }
+
+        let path = self.search(key.0, key.1).map(Arc::new);
+
+        if self.cache_size > 0 {
+            if let Ok(mut cache) = self.cache.lock() {
+                if !cache.paths.contains_key(&key) {
+                    if cache.order.len() >= self.cache_size {
+                        if let Some(oldest) = cache.order.pop_front() {
+                            cache.paths.remove(&oldest);
+                        }
+                    }
+                    cache.order.push_back(key);
+                }
+                cache.paths.insert(key, path.clone());
+            }
+        }
+
+        path
+    }
+}
+
+/// Tracks a path handed back by the game and steers along its waypoints
+#[derive(Default)]
+pub struct PathFollower {
+    waypoints: VecDeque<Vector3<f32>>,
+    goal: Option<Vector3<f32>>,
+    requested_at: Option<Instant>,
+}
+
+impl PathFollower {
+    pub fn needs_path(&self, target: &Vector3<f32>) -> bool {
+        match (self.goal, self.requested_at) {
+            (Some(goal), Some(requested_at)) => {
+                goal.metric_distance(target) > REPATH_DISTANCE
+                    || requested_at.elapsed() > REPATH_INTERVAL
+            }
+            _ => true,
+        }
+    }
+
+    pub fn request(&mut self, target: Vector3<f32>) -> Vector3<f32> {
+        self.goal = Some(target);
+        self.requested_at = Some(Instant::now());
+        target
+    }
+
+    pub fn set_path(&mut self, path: Option<Path>) {
+        self.waypoints = path
+            .map(|path| path.iter().cloned().collect())
+            .unwrap_or_default();
+    }
+
+    pub fn clear(&mut self) {
+        self.waypoints.clear();
+        self.goal = None;
+        self.requested_at = None;
+    }
+
+    pub fn steer(&mut self, actor: &FrozenGameObject, divisor: f32) -> Option<Vector3<f32>> {
+        while let Some(waypoint) = self.waypoints.front() {
+            let mut offset = waypoint - actor.position;
+            offset.y = 0.0;
+            if offset.magnitude() > WAYPOINT_REACHED {
+                return Some((offset - actor.velocity) / divisor);
+            }
+            self.waypoints.pop_front();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flat grassland with an ocean wall down x = 5, open at the far end when `gap` is set
+    fn walled_terrain(gap: bool) -> Terrain {
+        let size = 10;
+        let mut terrain_map = vec![TerrainType::Grassland as u8; (size * size) as usize];
+        for z in 0..size {
+            if !(gap && z == size - 1) {
+                terrain_map[(5 * size + z) as usize] = TerrainType::Ocean as u8;
+            }
+        }
+        Terrain {
+            size,
+            elevation_map: vec![0.0; (size * size) as usize],
+            terrain_map,
+            seed: 0,
+        }
+    }
+
+    fn pathfinder(terrain: &Terrain) -> Pathfinder {
+        let config = PathfindingConfig {
+            cell_size: 1,
+            ..PathfindingConfig::default()
+        };
+        Pathfinder::new(terrain, &config)
+    }
+
+    #[test]
+    fn find_path_goes_around_water() {
+        let pathfinder = pathfinder(&walled_terrain(true));
+        let from = Vector3::new(1.5, 0.0, 1.5);
+        let to = Vector3::new(8.5, 0.0, 1.5);
+
+        let path = pathfinder
+            .find_path(&from, &to)
+            .expect("a path through the gap");
+        assert_eq!(path.first(), Some(&from));
+        assert_eq!(path.last(), Some(&to));
+        assert!(path.iter().all(|waypoint| pathfinder.is_walkable(waypoint)));
+        assert!(path.iter().any(|waypoint| waypoint.z > 8.0));
+    }
+
+    #[test]
+    fn find_path_gives_up_when_walled_off() {
+        let pathfinder = pathfinder(&walled_terrain(false));
+
+        let path = pathfinder.find_path(&Vector3::new(1.5, 0.0, 1.5), &Vector3::new(8.5, 0.0, 1.5));
+        assert!(path.is_none());
+    }
+
+    #[test]
+    fn find_path_reuses_cached_searches() {
+        let pathfinder = pathfinder(&walled_terrain(true));
+        let from = Vector3::new(1.5, 0.0, 1.5);
+        let to = Vector3::new(8.5, 0.0, 1.5);
+
+        let first = pathfinder.find_path(&from, &to).unwrap();
+        let second = pathfinder.find_path(&from, &to).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+
+    #[test]
+    fn lookups_use_the_generated_layout() {
+        let size = 10;
+        let mut terrain_map = vec![TerrainType::Grassland as u8; (size * size) as usize];
+        terrain_map[(2 * size + 7) as usize] = TerrainType::Ocean as u8;
+        let terrain = Terrain {
+            size,
+            elevation_map: vec![0.0; (size * size) as usize],
+            terrain_map,
+            seed: 0,
+        };
+
+        assert_eq!(terrain.get_terrain_type(2, 7), TerrainType::Ocean);
+        assert_eq!(terrain.get_terrain_type(7, 2), TerrainType::Grassland);
+
+        let pathfinder = pathfinder(&terrain);
+        assert!(!pathfinder.is_walkable(&Vector3::new(2.5, 0.0, 7.5)));
+        assert!(pathfinder.is_walkable(&Vector3::new(7.5, 0.0, 2.5)));
+    }
+}
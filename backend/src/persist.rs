@@ -0,0 +1,35 @@
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Reads a JSON file, logging and returning None if it is missing or broken
+pub fn load_json<T: DeserializeOwned>(path: &Path, what: &str) -> Option<T> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::info!("no {} yet, could not read {:?}: {}", what, path, e);
+            return None;
+        }
+    };
+
+    match serde_json::from_str(&json) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            tracing::error!("ignoring broken {} {:?}: {}", what, path, e);
+            None
+        }
+    }
+}
+
+/// Writes next to the real file first so a crash mid-write can't lose the old copy
+pub fn save_json_atomic<T: Serialize>(
+    path: &Path,
+    value: &T,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let json = serde_json::to_string_pretty(value)?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
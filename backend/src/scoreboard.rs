@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::{Duration, Instant};
+
+use crate::persist::{load_json, save_json_atomic};
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerStats {
+    pub kills: u32,
+    pub deaths: u32,
+    pub damage_dealt: u32,
+    pub survival_ms: u64,
+    pub pickups: u32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScoreboardEntry {
+    pub object_id: u32,
+    pub username: String,
+    pub stats: PlayerStats,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LeaderboardEntry {
+    pub username: String,
+    pub stats: PlayerStats,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreboardConfig {
+    /// Resend the scoreboard this often even if nothing changed
+    pub push_interval_ms: u64,
+    /// Don't resend on every change faster than this
+    pub min_push_interval_ms: u64,
+    pub save_interval_ms: u64,
+    pub leaderboard_path: PathBuf,
+    pub leaderboard_size: usize,
+}
+
+impl Default for ScoreboardConfig {
+    fn default() -> ScoreboardConfig {
+        ScoreboardConfig {
+            push_interval_ms: 5000,
+            min_push_interval_ms: 250,
+            save_interval_ms: 30000,
+            leaderboard_path: PathBuf::from("leaderboard.json"),
+            leaderboard_size: 10,
+        }
+    }
+}
+
+struct Session {
+    username: String,
+    stats: PlayerStats,
+    alive_since: Option<Instant>,
+}
+
+/// Stats for everyone playing now, folded into all-time totals by username
+pub struct Scoreboard {
+    pub config: ScoreboardConfig,
+    sessions: HashMap<u32, Session>,
+    totals: HashMap<String, PlayerStats>,
+    changed: bool,
+    unsaved: bool,
+    last_push: Instant,
+    last_save: Instant,
+}
+
+impl Scoreboard {
+    pub fn new(config: &ScoreboardConfig) -> Scoreboard {
+        Scoreboard {
+            config: config.clone(),
+            sessions: HashMap::new(),
+            totals: load_json(&config.leaderboard_path, "leaderboard").unwrap_or_default(),
+            changed: false,
+            unsaved: false,
+            last_push: Instant::now(),
+            last_save: Instant::now(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: &ScoreboardConfig) {
+        self.config = config.clone();
+    }
+
+    pub fn join(&mut self, object_id: u32, username: &str) {
+        self.sessions.insert(
+            object_id,
+            Session {
+                username: username.to_string(),
+                stats: PlayerStats::default(),
+                alive_since: Some(Instant::now()),
+            },
+        );
+        self.changed = true;
+    }
+
+    pub fn leave(&mut self, object_id: u32) {
+        self.died(object_id, false);
+        self.sessions.remove(&object_id);
+        self.changed = true;
+    }
+
+    fn record(&mut self, object_id: u32, update: impl Fn(&mut PlayerStats)) {
+        let session = match self.sessions.get_mut(&object_id) {
+            Some(session) => session,
+            None => return,
+        };
+        update(&mut session.stats);
+        update(self.totals.entry(session.username.clone()).or_default());
+        self.changed = true;
+        self.unsaved = true;
+    }
+
+    pub fn kill(&mut self, object_id: u32) {
+        self.record(object_id, |stats| stats.kills += 1);
+    }
+
+    pub fn damage(&mut self, object_id: u32, amount: u8) {
+        self.record(object_id, |stats| stats.damage_dealt += amount as u32);
+    }
+
+    pub fn pickup(&mut self, object_id: u32) {
+        self.record(object_id, |stats| stats.pickups += 1);
+    }
+
+    /// Stops the survival clock, counting a death unless the player just left
+    pub fn died(&mut self, object_id: u32, counts: bool) {
+        let alive_ms = match self
+            .sessions
+            .get_mut(&object_id)
+            .and_then(|session| session.alive_since.take())
+        {
+            Some(alive_since) => alive_since.elapsed().as_millis() as u64,
+            None => return,
+        };
+        let deaths = if counts { 1 } else { 0 };
+        self.record(object_id, |stats| {
+            stats.deaths += deaths;
+            stats.survival_ms += alive_ms;
+        });
+    }
+
+    pub fn respawned(&mut self, object_id: u32) {
+        if let Some(session) = self.sessions.get_mut(&object_id) {
+            session.alive_since = Some(Instant::now());
+        }
+    }
+
+    /// The current scoreboard if it is time to push one out
+    pub fn due_push(&mut self) -> Option<Vec<ScoreboardEntry>> {
+        let since_push = self.last_push.elapsed();
+        let due = since_push >= Duration::from_millis(self.config.push_interval_ms)
+            || (self.changed
+                && since_push >= Duration::from_millis(self.config.min_push_interval_ms));
+        if !due {
+            return None;
+        }
+
+        self.changed = false;
+        self.last_push = Instant::now();
+        Some(self.entries())
+    }
+
+    pub fn entries(&self) -> Vec<ScoreboardEntry> {
+        let mut entries: Vec<ScoreboardEntry> = self
+            .sessions
+            .iter()
+            .map(|(object_id, session)| {
+                let mut stats = session.stats.clone();
+                if let Some(alive_since) = session.alive_since {
+                    stats.survival_ms += alive_since.elapsed().as_millis() as u64;
+                }
+                ScoreboardEntry {
+                    object_id: *object_id,
+                    username: session.username.clone(),
+                    stats,
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.stats
+                .kills
+                .cmp(&a.stats.kills)
+                .then(a.stats.deaths.cmp(&b.stats.deaths))
+        });
+        entries
+    }
+
+    /// Best all-time players by kills
+    pub fn leaderboard(&self) -> Vec<LeaderboardEntry> {
+        let mut entries: Vec<LeaderboardEntry> = self
+            .totals
+            .iter()
+            .map(|(username, stats)| LeaderboardEntry {
+                username: username.clone(),
+                stats: stats.clone(),
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.stats
+                .kills
+                .cmp(&a.stats.kills)
+                .then(b.stats.survival_ms.cmp(&a.stats.survival_ms))
+        });
+        entries.truncate(self.config.leaderboard_size);
+        entries
+    }
+
+    pub fn save_if_due(&mut self) {
+        if self.unsaved
+            && self.last_save.elapsed() >= Duration::from_millis(self.config.save_interval_ms)
+        {
+            self.save();
+        }
+    }
+
+    pub fn save(&mut self) {
+        self.last_save = Instant::now();
+        match save_json_atomic(&self.config.leaderboard_path, &self.totals) {
+            Ok(()) => self.unsaved = false,
+            Err(e) => tracing::error!(
+                "error saving leaderboard {:?}: {}",
+                self.config.leaderboard_path,
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scoreboard(name: &str) -> Scoreboard {
+        let path = std::env::temp_dir().join(format!("{}-{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Scoreboard::new(&ScoreboardConfig {
+            leaderboard_path: path,
+            leaderboard_size: 2,
+            ..ScoreboardConfig::default()
+        })
+    }
+
+    #[test]
+    fn entries_rank_by_kills_then_fewest_deaths() {
+        let mut scoreboard = scoreboard("scoreboard-entries");
+        scoreboard.join(1, "ann");
+        scoreboard.join(2, "bob");
+        scoreboard.join(3, "cat");
+        scoreboard.kill(2);
+        scoreboard.kill(3);
+        scoreboard.died(3, true);
+
+        let ranked: Vec<u32> = scoreboard
+            .entries()
+            .iter()
+            .map(|entry| entry.object_id)
+            .collect();
+        assert_eq!(ranked, vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn leaderboard_keeps_the_best_totals_across_sessions() {
+        let mut scoreboard = scoreboard("scoreboard-leaderboard");
+        scoreboard.join(1, "ann");
+        scoreboard.kill(1);
+        scoreboard.leave(1);
+        scoreboard.join(2, "ann");
+        scoreboard.kill(2);
+        scoreboard.join(3, "bob");
+        scoreboard.join(4, "cat");
+        scoreboard.kill(4);
+
+        let leaderboard = scoreboard.leaderboard();
+        assert_eq!(leaderboard.len(), 2);
+        assert_eq!(leaderboard[0].username, "ann");
+        assert_eq!(leaderboard[0].stats.kills, 2);
+        assert_eq!(leaderboard[1].username, "cat");
+    }
+
+    #[test]
+    fn totals_survive_a_restart() {
+        let mut scoreboard = scoreboard("scoreboard-restart");
+        scoreboard.join(1, "ann");
+        scoreboard.kill(1);
+        scoreboard.damage(1, 30);
+        scoreboard.save();
+
+        let reloaded = Scoreboard::new(&scoreboard.config);
+        let leaderboard = reloaded.leaderboard();
+        assert_eq!(leaderboard.len(), 1);
+        assert_eq!(leaderboard[0].username, "ann");
+        assert_eq!(leaderboard[0].stats.kills, 1);
+        assert_eq!(leaderboard[0].stats.damage_dealt, 30);
+
+        let _ = std::fs::remove_file(&scoreboard.config.leaderboard_path);
+    }
+}
@@ -0,0 +1,187 @@
+use std::path::{Path, PathBuf};
+
+use nalgebra::Vector3;
+
+use serde::{Deserialize, Serialize};
+
+use crate::actor::ActorType;
+use crate::effects::StatusEffect;
+use crate::game::ObjectType;
+use crate::persist::{load_json, save_json_atomic};
+
+/// Bumped whenever the layout changes, older snapshots are ignored rather than misread
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotConfig {
+    pub path: PathBuf,
+    /// Pick up where the last snapshot left off instead of generating a new world
+    pub restore_on_start: bool,
+    pub save_on_shutdown: bool,
+    /// Also save this often while running, zero to only save on shutdown
+    pub save_interval_ms: u64,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> SnapshotConfig {
+        SnapshotConfig {
+            path: PathBuf::from("world.snapshot.json"),
+            restore_on_start: true,
+            save_on_shutdown: true,
+            save_interval_ms: 300000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedActor {
+    pub actor_id: u32,
+    pub actor_type: ActorType,
+    /// Spawn zone the actor counts toward, if the spawner made it
+    pub zone: Option<usize>,
+}
+
+/// An actor or item with the components needed to rebuild it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedObject {
+    pub object_id: u32,
+    pub object_type: ObjectType,
+    pub alive: bool,
+    pub age: u32,
+    pub health: u8,
+    pub position: Vector3<f32>,
+    pub velocity: Vector3<f32>,
+    pub acceleration: Vector3<f32>,
+    pub item: Option<String>,
+    pub effects: Vec<StatusEffect>,
+    /// How long a corpse had been lying there
+    pub dead_for_ms: Option<u64>,
+    pub actor: Option<SavedActor>,
+}
+
+/// Everything needed to bring a world back after a restart, players excepted
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version: u32,
+    pub seed: u32,
+    pub size: u32,
+    pub ticks: u32,
+    pub round: u32,
+    pub objects: Vec<SavedObject>,
+}
+
+pub fn load_snapshot(path: &Path) -> Option<Snapshot> {
+    let snapshot: Snapshot = load_json(path, "snapshot")?;
+
+    if snapshot.version != SNAPSHOT_VERSION {
+        tracing::warn!(
+            "ignoring snapshot {:?} with version {}, expected {}",
+            path,
+            snapshot.version,
+            SNAPSHOT_VERSION
+        );
+        return None;
+    }
+    Some(snapshot)
+}
+
+pub fn save_snapshot(path: &Path, snapshot: &Snapshot) -> Result<(), Box<dyn std::error::Error>> {
+    save_json_atomic(path, snapshot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::effects::EffectKind;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("crashtv-{}-{}.json", name, std::process::id()))
+    }
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            seed: 42,
+            size: 100,
+            ticks: 1234,
+            round: 3,
+            objects: vec![SavedObject {
+                object_id: 7,
+                object_type: ObjectType::Actor,
+                alive: true,
+                age: 10,
+                health: 80,
+                position: Vector3::new(1.0, 2.0, 3.0),
+                velocity: Vector3::new(0.5, 0.0, -0.5),
+                acceleration: Vector3::new(0.0, 0.0, 0.0),
+                item: None,
+                effects: vec![StatusEffect {
+                    kind: EffectKind::Poison,
+                    magnitude: 4.0,
+                    stacks: 2,
+                    remaining_ms: 1500,
+                    source: Some(9),
+                }],
+                dead_for_ms: None,
+                actor: Some(SavedActor {
+                    actor_id: 5,
+                    actor_type: ActorType::Guard,
+                    zone: Some(1),
+                }),
+            }],
+        }
+    }
+
+    #[test]
+    fn snapshot_survives_a_save_and_load() {
+        let path = temp_path("snapshot-round-trip");
+        save_snapshot(&path, &snapshot()).unwrap();
+        let loaded = load_snapshot(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let loaded = loaded.expect("the snapshot just saved");
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.size, 100);
+        assert_eq!(loaded.ticks, 1234);
+        assert_eq!(loaded.round, 3);
+        assert_eq!(loaded.objects.len(), 1);
+
+        let object = &loaded.objects[0];
+        assert_eq!(object.object_id, 7);
+        assert!(matches!(object.object_type, ObjectType::Actor));
+        assert_eq!(object.health, 80);
+        assert_eq!(object.position, Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(object.velocity, Vector3::new(0.5, 0.0, -0.5));
+
+        let effect = &object.effects[0];
+        assert_eq!(effect.kind, EffectKind::Poison);
+        assert_eq!(effect.stacks, 2);
+        assert_eq!(effect.remaining_ms, 1500);
+        // who applied an effect isn't worth keeping across a restart
+        assert_eq!(effect.source, None);
+
+        let actor = object.actor.as_ref().unwrap();
+        assert_eq!(actor.actor_id, 5);
+        assert_eq!(actor.actor_type, ActorType::Guard);
+        assert_eq!(actor.zone, Some(1));
+    }
+
+    #[test]
+    fn snapshots_from_another_version_are_ignored() {
+        let path = temp_path("snapshot-old-version");
+        let mut old = snapshot();
+        old.version = SNAPSHOT_VERSION + 1;
+        save_snapshot(&path, &old).unwrap();
+        let loaded = load_snapshot(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(loaded.is_none());
+    }
+
+    #[test]
+    fn missing_snapshots_load_as_none() {
+        assert!(load_snapshot(&temp_path("snapshot-missing")).is_none());
+    }
+}
@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use nalgebra::Vector3;
+
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::Instant;
+
+use crate::actor::ActorType;
+use crate::pathfinding::Pathfinder;
+use crate::terrain::{Terrain, TerrainType};
+
+/// A population of one actor type to keep alive, optionally limited to some biomes
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SpawnZone {
+    pub actor_type: ActorType,
+    pub population: u32,
+    #[serde(default)]
+    pub biomes: Vec<TerrainType>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpawnerConfig {
+    pub max_spawns_per_tick: usize,
+    pub min_player_distance: f32,
+    pub min_spacing: f32,
+    pub max_attempts: usize,
+    pub zones: Vec<SpawnZone>,
+}
+
+impl Default for SpawnerConfig {
+    fn default() -> SpawnerConfig {
+        SpawnerConfig {
+            max_spawns_per_tick: 2,
+            min_player_distance: 60.0,
+            min_spacing: 5.0,
+            max_attempts: 20,
+            zones: Vec::new(),
+        }
+    }
+}
+
+/// Keeps each spawn zone topped up, remembering which zone every actor came from
+pub struct Spawner {
+    pub config: SpawnerConfig,
+    owners: HashMap<u32, usize>,
+    cooldowns: Vec<(usize, Instant)>,
+}
+
+impl Spawner {
+    pub fn new(config: &SpawnerConfig) -> Spawner {
+        Spawner {
+            config: config.clone(),
+            owners: HashMap::new(),
+            cooldowns: Vec::new(),
+        }
+    }
+
+    pub fn set_config(&mut self, config: &SpawnerConfig) {
+        self.config = config.clone();
+    }
+
+    pub fn claim(&mut self, actor_id: u32, zone: usize) {
+        self.owners.insert(actor_id, zone);
+    }
+
+    pub fn zone_of(&self, actor_id: u32) -> Option<usize> {
+        self.owners.get(&actor_id).cloned()
+    }
+
+    /// Frees the actor's slot in its zone, which can't be refilled until `respawn_at`
+    pub fn release(&mut self, actor_id: u32, respawn_at: Option<Instant>) -> Option<usize> {
+        let zone = self.owners.remove(&actor_id)?;
+        if let Some(respawn_at) = respawn_at {
+            self.cooldowns.push((zone, respawn_at));
+        }
+        Some(zone)
+    }
+
+    /// Zones to spawn into this tick, throttled and spread across zones
+    pub fn due_spawns(&mut self) -> Vec<(usize, ActorType)> {
+        let now = Instant::now();
+        self.cooldowns.retain(|(_, respawn_at)| *respawn_at > now);
+
+        let mut deficits: Vec<(usize, u32)> = self
+            .config
+            .zones
+            .iter()
+            .enumerate()
+            .map(|(zone, spawn_zone)| {
+                let living = self.owners.values().filter(|owner| **owner == zone).count();
+                let cooling = self.cooldowns.iter().filter(|(z, _)| *z == zone).count();
+                let deficit = spawn_zone
+                    .population
+                    .saturating_sub((living + cooling) as u32);
+                (zone, deficit)
+            })
+            .filter(|(_, deficit)| *deficit > 0)
+            .collect();
+
+        let mut due = Vec::new();
+        while due.len() < self.config.max_spawns_per_tick && !deficits.is_empty() {
+            deficits.retain_mut(|(zone, deficit)| {
+                if due.len() >= self.config.max_spawns_per_tick {
+                    return true;
+                }
+                due.push((*zone, self.config.zones[*zone].actor_type.clone()));
+                *deficit -= 1;
+                *deficit > 0
+            });
+        }
+        due
+    }
+
+    pub fn zone_biomes(&self, zone: usize) -> &[TerrainType] {
+        self.config
+            .zones
+            .get(zone)
+            .map(|spawn_zone| spawn_zone.biomes.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Picks a random walkable point in one of `biomes` (any biome if empty),
+    /// away from players and not crowding other actors
+    pub fn find_spawn_point(
+        &self,
+        terrain: &Terrain,
+        pathfinder: &Pathfinder,
+        biomes: &[TerrainType],
+        players: &[Vector3<f32>],
+        occupied: &[Vector3<f32>],
+    ) -> Option<Vector3<f32>> {
+        self.sample_point(terrain, pathfinder, |position, terrain_type| {
+            if !biomes.is_empty() && !biomes.contains(&terrain_type) {
+                return false;
+            }
+
+            !players
+                .iter()
+                .any(|player| flat_distance(player, position) < self.config.min_player_distance)
+                && !occupied
+                    .iter()
+                    .any(|other| flat_distance(other, position) < self.config.min_spacing)
+        })
+    }
+
+    /// Picks a random walkable point at least `clearance` from everything in `avoid`
+    pub fn find_clear_point(
+        &self,
+        terrain: &Terrain,
+        pathfinder: &Pathfinder,
+        avoid: &[Vector3<f32>],
+        clearance: f32,
+    ) -> Option<Vector3<f32>> {
+        self.sample_point(terrain, pathfinder, |position, _| {
+            !avoid
+                .iter()
+                .any(|other| flat_distance(other, position) < clearance)
+        })
+    }
+
+    fn sample_point<F>(
+        &self,
+        terrain: &Terrain,
+        pathfinder: &Pathfinder,
+        accept: F,
+    ) -> Option<Vector3<f32>>
+    where
+        F: Fn(&Vector3<f32>, TerrainType) -> bool,
+    {
+        let mut rng = rand::thread_rng();
+        let size = terrain.size as f32;
+
+        for _ in 0..self.config.max_attempts {
+            let x = rng.gen::<f32>() * size;
+            let z = rng.gen::<f32>() * size;
+            let cell_x = x.clamp(0.0, size - 1.0) as u32;
+            let cell_z = z.clamp(0.0, size - 1.0) as u32;
+            let position = Vector3::new(x, terrain.get_elevation(cell_x, cell_z), z);
+
+            if !pathfinder.is_walkable(&position) {
+                continue;
+            }
+
+            if accept(&position, terrain.get_terrain_type(cell_x, cell_z)) {
+                return Some(position);
+            }
+        }
+
+        None
+    }
+}
+
+fn flat_distance(a: &Vector3<f32>, b: &Vector3<f32>) -> f32 {
+    Vector3::new(a.x - b.x, 0.0, a.z - b.z).magnitude()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::pathfinding::PathfindingConfig;
+
+    fn zone(actor_type: ActorType, population: u32) -> SpawnZone {
+        SpawnZone {
+            actor_type,
+            population,
+            biomes: Vec::new(),
+        }
+    }
+
+    fn spawner(max_spawns_per_tick: usize, zones: Vec<SpawnZone>) -> Spawner {
+        Spawner::new(&SpawnerConfig {
+            max_spawns_per_tick,
+            zones,
+            ..SpawnerConfig::default()
+        })
+    }
+
+    /// Beach below x = 10 and grassland above, or all `fill` when given
+    fn terrain(fill: Option<TerrainType>) -> Terrain {
+        let size = 20;
+        let terrain_map = (0..size * size)
+            .map(|idx| match fill {
+                Some(terrain_type) => terrain_type as u8,
+                None if idx / size < size / 2 => TerrainType::Beach as u8,
+                None => TerrainType::Grassland as u8,
+            })
+            .collect();
+        Terrain {
+            size,
+            elevation_map: vec![0.0; (size * size) as usize],
+            terrain_map,
+            seed: 0,
+        }
+    }
+
+    fn pathfinder(terrain: &Terrain) -> Pathfinder {
+        let config = PathfindingConfig {
+            cell_size: 1,
+            ..PathfindingConfig::default()
+        };
+        Pathfinder::new(terrain, &config)
+    }
+
+    #[test]
+    fn respawn_points_keep_clear_of_enemies() {
+        let terrain = terrain(Some(TerrainType::Grassland));
+        let pathfinder = pathfinder(&terrain);
+        let spawner = spawner(1, Vec::new());
+        let enemy = Vector3::new(10.0, 0.0, 10.0);
+
+        for _ in 0..20 {
+            let position = spawner
+                .find_clear_point(&terrain, &pathfinder, &[enemy], 5.0)
+                .expect("most of the map is clear");
+            assert!(flat_distance(&enemy, &position) >= 5.0);
+        }
+        assert!(spawner
+            .find_clear_point(&terrain, &pathfinder, &[enemy], 100.0)
+            .is_none());
+    }
+
+    #[test]
+    fn due_spawns_spreads_the_throttle_across_zones() {
+        let mut spawner = spawner(
+            3,
+            vec![zone(ActorType::Walker, 3), zone(ActorType::Hunter, 1)],
+        );
+
+        let due: Vec<usize> = spawner.due_spawns().into_iter().map(|(z, _)| z).collect();
+        assert_eq!(due, vec![0, 1, 0]);
+    }
+
+    #[test]
+    fn due_spawns_counts_living_and_cooling_down_actors() {
+        let mut spawner = spawner(5, vec![zone(ActorType::Walker, 2)]);
+        spawner.claim(1, 0);
+        spawner.claim(2, 0);
+        assert!(spawner.due_spawns().is_empty());
+
+        let later = Instant::now() + std::time::Duration::from_secs(60);
+        assert_eq!(spawner.release(1, Some(later)), Some(0));
+        assert!(spawner.due_spawns().is_empty());
+
+        assert_eq!(spawner.release(2, None), Some(0));
+        assert_eq!(spawner.due_spawns().len(), 1);
+    }
+
+    #[test]
+    fn find_spawn_point_stays_in_the_zone_biomes() {
+        let terrain = terrain(None);
+        let pathfinder = pathfinder(&terrain);
+        let spawner = spawner(1, Vec::new());
+
+        for _ in 0..20 {
+            let position = spawner
+                .find_spawn_point(&terrain, &pathfinder, &[TerrainType::Beach], &[], &[])
+                .expect("half the map is beach");
+            let terrain_type = terrain.get_terrain_type(position.x as u32, position.z as u32);
+            assert_eq!(terrain_type, TerrainType::Beach);
+        }
+    }
+
+    #[test]
+    fn find_spawn_point_gives_up_without_a_valid_spot() {
+        let ocean = terrain(Some(TerrainType::Ocean));
+        let spawner = spawner(1, Vec::new());
+        assert!(spawner
+            .find_spawn_point(&ocean, &pathfinder(&ocean), &[], &[], &[])
+            .is_none());
+
+        let land = terrain(Some(TerrainType::Grassland));
+        let player = Vector3::new(10.0, 0.0, 10.0);
+        assert!(spawner
+            .find_spawn_point(&land, &pathfinder(&land), &[], &[player], &[])
+            .is_none());
+    }
+}
@@ -0,0 +1,58 @@
+use nalgebra::Vector3;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::game::{Client, GameResponse};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpectatorConfig {
+    pub max_spectators: usize,
+    /// How far around the followed object updates reach unless watching the full map
+    pub view_range: f32,
+}
+
+impl Default for SpectatorConfig {
+    fn default() -> SpectatorConfig {
+        SpectatorConfig {
+            max_spectators: 16,
+            view_range: 150.0,
+        }
+    }
+}
+
+/// Connection watching the game without an object in the world
+#[derive(Debug)]
+pub struct Spectator {
+    pub client: Client,
+    pub conn: UnboundedSender<GameResponse>,
+    pub full_map: bool,
+    pub following: Option<u32>,
+}
+
+impl Spectator {
+    pub fn send(&self, response: GameResponse) {
+        let result = self.conn.send(response);
+        if let Err(e) = result {
+            tracing::error!("game response write error {:?}: {}", self.client, e);
+        }
+    }
+
+    /// Whether an object at `position` is worth sending given where the followed object is
+    pub fn sees(
+        &self,
+        focus: Option<&Vector3<f32>>,
+        position: &Vector3<f32>,
+        view_range: f32,
+    ) -> bool {
+        if self.full_map {
+            return true;
+        }
+        match focus {
+            Some(focus) => focus.metric_distance(position) <= view_range,
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::HashSet;
+
+use nalgebra::Vector3;
+
+use rand::Rng;
+
+use serde::{Deserialize, Serialize};
+
+use tokio::time::{Duration, Instant};
+
+use crate::actor::ActorType;
+use crate::game::{GameArea, GameResponse};
+use crate::mode::{GameMode, ModeKind, Outcome, RoundState};
+use crate::pathfinding::Pathfinder;
+use crate::teams::TeamConfig;
+use crate::terrain::Terrain;
+
+/// An actor type that joins the waves from `from_wave` on
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WaveEntry {
+    pub actor_type: ActorType,
+    pub from_wave: u32,
+    pub weight: u32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SurvivalConfig {
+    pub first_wave_size: u32,
+    /// Extra actors added to each wave after the first
+    pub wave_growth: u32,
+    /// Breather between clearing a wave and the next one arriving
+    pub wave_interval_ms: u64,
+    pub spawn_radius_min: f32,
+    pub spawn_radius_max: f32,
+    pub max_attempts: usize,
+    pub waves: Vec<WaveEntry>,
+}
+
+impl Default for SurvivalConfig {
+    fn default() -> SurvivalConfig {
+        SurvivalConfig {
+            first_wave_size: 10,
+            wave_growth: 5,
+            wave_interval_ms: 10000,
+            spawn_radius_min: 40.0,
+            spawn_radius_max: 80.0,
+            max_attempts: 20,
+            waves: vec![
+                WaveEntry {
+                    actor_type: ActorType::Walker,
+                    from_wave: 1,
+                    weight: 4,
+                },
+                WaveEntry {
+                    actor_type: ActorType::Hunter,
+                    from_wave: 3,
+                    weight: 2,
+                },
+                WaveEntry {
+                    actor_type: ActorType::Guard,
+                    from_wave: 5,
+                    weight: 1,
+                },
+            ],
+        }
+    }
+}
+
+/// How long the players held out
+#[derive(Clone, Debug, Serialize)]
+pub struct RoundStats {
+    pub waves: u32,
+    pub survived_ms: u64,
+    pub kills: u32,
+}
+
+/// Escalating waves of actors spawned around the players until none are left standing
+pub struct SurvivalMode {
+    started_at: Instant,
+    wave: u32,
+    wave_actors: HashSet<u32>,
+    next_wave_at: Option<Instant>,
+    kills: u32,
+}
+
+impl Default for SurvivalMode {
+    fn default() -> SurvivalMode {
+        SurvivalMode {
+            started_at: Instant::now(),
+            wave: 0,
+            wave_actors: HashSet::new(),
+            next_wave_at: None,
+            kills: 0,
+        }
+    }
+}
+
+impl SurvivalMode {
+    /// Forgets wave actors that are gone, scheduling the next wave once all are,
+    /// and returns the number and size of a wave that is due now
+    fn due_wave(
+        &mut self,
+        config: &SurvivalConfig,
+        is_alive: impl Fn(u32) -> bool,
+    ) -> Option<(u32, u32)> {
+        self.wave_actors.retain(|actor_id| is_alive(*actor_id));
+        if self.wave_actors.is_empty() && self.next_wave_at.is_none() {
+            self.next_wave_at =
+                Some(Instant::now() + Duration::from_millis(config.wave_interval_ms));
+        }
+
+        match self.next_wave_at {
+            Some(next_wave_at) if Instant::now() >= next_wave_at => {
+                self.next_wave_at = None;
+                self.wave += 1;
+                let size = config.first_wave_size + config.wave_growth * (self.wave - 1);
+                Some((self.wave, size))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl GameMode for SurvivalMode {
+    fn kind(&self) -> ModeKind {
+        ModeKind::Survival
+    }
+
+    fn team_count(&self, _config: &TeamConfig) -> u32 {
+        1
+    }
+
+    fn allows_respawn(&self, state: RoundState) -> bool {
+        state != RoundState::Playing
+    }
+
+    fn on_round_start(&mut self, _area: &mut GameArea) {
+        *self = SurvivalMode::default();
+        self.next_wave_at = Some(Instant::now());
+    }
+
+    fn on_round_end(&mut self, area: &mut GameArea) {
+        for actor_id in self.wave_actors.drain() {
+            area.kill_actor(actor_id);
+        }
+    }
+
+    fn on_kill(&mut self, area: &mut GameArea, victim: u32, killer: u32) {
+        if area.is_player(killer) && !area.is_player(victim) {
+            self.kills += 1;
+        }
+    }
+
+    fn on_tick(&mut self, area: &mut GameArea) {
+        let living = area.living_player_positions();
+        if living.is_empty() {
+            return;
+        }
+
+        let config = area.config.survival.clone();
+        let due = self.due_wave(&config, |actor_id| area.is_actor_alive(actor_id));
+        let (wave, size) = match due {
+            Some(due) => due,
+            None => return,
+        };
+
+        let mut rng = rand::thread_rng();
+        let mut spawned = 0;
+        for _ in 0..size {
+            let center = living[rng.gen_range(0..living.len())];
+            let actor_type = match wave_actor_type(&config, wave) {
+                Some(actor_type) => actor_type,
+                None => break,
+            };
+            let position = match spawn_point(&config, &area.terrain, &area.pathfinder, &center) {
+                Some(position) => position,
+                None => continue,
+            };
+
+            let actor_id = area.spawn_actor_at(actor_type, position);
+            self.wave_actors.insert(actor_id);
+            spawned += 1;
+
+            if let Some(actor) = area.actors.get(&actor_id) {
+                area.broadcast_object(actor.object_id);
+            }
+        }
+
+        tracing::info!("survival wave {} with {} actors", wave, spawned);
+        area.broadcast(GameResponse::WaveStarted(wave, spawned));
+    }
+
+    fn check_victory(&self, area: &GameArea) -> Option<Outcome> {
+        if !area.living_player_positions().is_empty() {
+            return None;
+        }
+
+        Some(Outcome::Survived(RoundStats {
+            waves: self.wave,
+            survived_ms: self.started_at.elapsed().as_millis() as u64,
+            kills: self.kills,
+        }))
+    }
+}
+
+/// Picks an actor type allowed in `wave`, favoring the heavier entries
+fn wave_actor_type(config: &SurvivalConfig, wave: u32) -> Option<ActorType> {
+    let entries: Vec<&WaveEntry> = config
+        .waves
+        .iter()
+        .filter(|entry| entry.from_wave <= wave)
+        .collect();
+    let total: u32 = entries.iter().map(|entry| entry.weight).sum();
+    if total == 0 {
+        return None;
+    }
+
+    let mut roll = rand::thread_rng().gen_range(0..total);
+    for entry in entries {
+        if roll < entry.weight {
+            return Some(entry.actor_type.clone());
+        }
+        roll -= entry.weight;
+    }
+    None
+}
+
+/// A walkable point in a ring around `center`
+fn spawn_point(
+    config: &SurvivalConfig,
+    terrain: &Terrain,
+    pathfinder: &Pathfinder,
+    center: &Vector3<f32>,
+) -> Option<Vector3<f32>> {
+    let mut rng = rand::thread_rng();
+    let size = terrain.size as f32;
+    let min = config.spawn_radius_min;
+    let max = config.spawn_radius_max.max(min);
+
+    for _ in 0..config.max_attempts {
+        let angle = rng.gen::<f32>() * std::f32::consts::TAU;
+        let radius = min + rng.gen::<f32>() * (max - min);
+        let x = center.x + angle.cos() * radius;
+        let z = center.z + angle.sin() * radius;
+        if x < 0.0 || z < 0.0 || x >= size || z >= size {
+            continue;
+        }
+
+        let position = Vector3::new(x, terrain.get_elevation(x as u32, z as u32), z);
+        if pathfinder.is_walkable(&position) {
+            return Some(position);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SurvivalConfig {
+        SurvivalConfig {
+            first_wave_size: 10,
+            wave_growth: 5,
+            wave_interval_ms: 0,
+            ..SurvivalConfig::default()
+        }
+    }
+
+    #[test]
+    fn waves_grow_and_wait_for_the_last_to_be_cleared() {
+        let config = config();
+        let mut survival = SurvivalMode::default();
+
+        assert_eq!(survival.due_wave(&config, |_| true), Some((1, 10)));
+        survival.wave_actors.insert(7);
+        assert_eq!(survival.due_wave(&config, |_| true), None);
+
+        assert_eq!(survival.due_wave(&config, |_| false), Some((2, 15)));
+    }
+
+    #[test]
+    fn later_actor_types_wait_for_their_wave() {
+        let config = config();
+
+        for _ in 0..20 {
+            assert!(matches!(
+                wave_actor_type(&config, 1),
+                Some(ActorType::Walker)
+            ));
+        }
+        assert!(wave_actor_type(
+            &SurvivalConfig {
+                waves: vec![],
+                ..config
+            },
+            1
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use bevy_ecs::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+/// Side a player is on, objects without one are hostile to everybody
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub struct Team(pub u32);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TeamConfig {
+    /// Sides in team-vs-team modes, cooperative modes put everyone on one
+    pub count: u32,
+    pub friendly_fire: bool,
+    /// Move players between teams when one gets this many more than another
+    pub max_imbalance: usize,
+}
+
+impl Default for TeamConfig {
+    fn default() -> TeamConfig {
+        TeamConfig {
+            count: 2,
+            friendly_fire: false,
+            max_imbalance: 2,
+        }
+    }
+}
+
+/// Head count of each of `count` teams given everyone's current team
+pub fn team_sizes(count: u32, members: impl Iterator<Item = Team>) -> HashMap<Team, usize> {
+    let mut sizes: HashMap<Team, usize> = (0..count).map(|team| (Team(team), 0)).collect();
+    for team in members {
+        if let Some(size) = sizes.get_mut(&team) {
+            *size += 1;
+        }
+    }
+    sizes
+}
+
+pub fn smallest_team(sizes: &HashMap<Team, usize>) -> Option<Team> {
+    sizes
+        .iter()
+        .min_by_key(|(team, size)| (**size, team.0))
+        .map(|(team, _)| *team)
+}
+
+pub fn largest_team(sizes: &HashMap<Team, usize>) -> Option<Team> {
+    sizes
+        .iter()
+        .max_by_key(|(team, size)| (**size, u32::MAX - team.0))
+        .map(|(team, _)| *team)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn team_sizes_counts_empty_teams_and_ignores_unknown_ones() {
+        let sizes = team_sizes(3, [Team(0), Team(0), Team(2), Team(7)].into_iter());
+
+        assert_eq!(sizes.len(), 3);
+        assert_eq!(sizes[&Team(0)], 2);
+        assert_eq!(sizes[&Team(1)], 0);
+        assert_eq!(sizes[&Team(2)], 1);
+    }
+
+    #[test]
+    fn new_players_join_the_smallest_team() {
+        let sizes = team_sizes(3, [Team(0), Team(1), Team(1)].into_iter());
+
+        assert_eq!(smallest_team(&sizes), Some(Team(2)));
+        assert_eq!(largest_team(&sizes), Some(Team(1)));
+    }
+
+    #[test]
+    fn ties_go_to_the_lowest_numbered_team() {
+        let sizes = team_sizes(3, [Team(2), Team(1)].into_iter());
+        assert_eq!(smallest_team(&sizes), Some(Team(0)));
+
+        let sizes = team_sizes(3, [Team(2), Team(1), Team(2), Team(1)].into_iter());
+        assert_eq!(largest_team(&sizes), Some(Team(1)));
+    }
+
+    #[test]
+    fn no_teams_means_nowhere_to_go() {
+        let sizes = team_sizes(0, [Team(0)].into_iter());
+
+        assert_eq!(smallest_team(&sizes), None);
+        assert_eq!(largest_team(&sizes), None);
+    }
+}
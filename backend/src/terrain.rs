@@ -1,5 +1,7 @@
 extern crate noise;
 
+use std::collections::HashMap;
+use std::io::Cursor;
 use std::path::Path;
 use std::thread;
 use std::time::Instant;
@@ -9,9 +11,9 @@ use rand::Rng;
 use noise::utils::{NoiseMap, NoiseMapBuilder, PlaneMapBuilder};
 use noise::{Fbm, Perlin};
 
-use image::{ImageBuffer, Rgb, RgbImage};
+use image::{ImageBuffer, ImageError, ImageOutputFormat, Rgb, RgbImage};
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TerrainType {
     Bare = 0x0,
     Beach = 0x1,
@@ -30,23 +32,83 @@ pub enum TerrainType {
     Tundra = 0xe,
 }
 
+impl From<u8> for TerrainType {
+    fn from(value: u8) -> TerrainType {
+        match value {
+            0x1 => TerrainType::Beach,
+            0x2 => TerrainType::Grassland,
+            0x3 => TerrainType::Ocean,
+            0x4 => TerrainType::Scorched,
+            0x5 => TerrainType::Shrubland,
+            0x6 => TerrainType::Snow,
+            0x7 => TerrainType::SubtropicalDesert,
+            0x8 => TerrainType::Taiga,
+            0x9 => TerrainType::TemperateDeciduousForest,
+            0xa => TerrainType::TemperateDesert,
+            0xb => TerrainType::TemperateRainForest,
+            0xc => TerrainType::TropicalRainForest,
+            0xd => TerrainType::TropicalSeasonalForest,
+            0xe => TerrainType::Tundra,
+            _ => TerrainType::Bare,
+        }
+    }
+}
+
+fn get_terrain_color(terrain_type: &TerrainType) -> Rgb<u8> {
+    match terrain_type {
+        TerrainType::Ocean => Rgb([0, 153, 152]),
+        TerrainType::Beach => Rgb([153, 255, 255]),
+        TerrainType::Bare => Rgb([128, 128, 128]),
+        TerrainType::Grassland => Rgb([102, 255, 102]),
+        TerrainType::Scorched => Rgb([192, 192, 192]),
+        TerrainType::Shrubland => Rgb([204, 204, 0]),
+        TerrainType::Snow => Rgb([255, 255, 255]),
+        TerrainType::SubtropicalDesert => Rgb([255, 204, 153]),
+        TerrainType::Taiga => Rgb([0, 204, 102]),
+        TerrainType::TemperateDeciduousForest => Rgb([102, 204, 0]),
+        TerrainType::TemperateDesert => Rgb([255, 153, 51]),
+        TerrainType::TemperateRainForest => Rgb([0, 204, 0]),
+        TerrainType::TropicalRainForest => Rgb([0, 255, 0]),
+        TerrainType::TropicalSeasonalForest => Rgb([51, 255, 51]),
+        TerrainType::Tundra => Rgb([204, 229, 255]),
+    }
+}
+
+/// Renders a biome map as laid out in `Terrain::terrain_map` to PNG bytes
+pub fn render_terrain_png(size: u32, terrain_map: &[u8]) -> Result<Vec<u8>, ImageError> {
+    let image: RgbImage = ImageBuffer::from_fn(size, size, |x, y| {
+        get_terrain_color(&TerrainType::from(terrain_map[(x * size + y) as usize]))
+    });
+
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)?;
+    Ok(png)
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub struct Terrain {
     pub size: u32,
     pub elevation_map: Vec<f32>,
     pub terrain_map: Vec<u8>,
+    pub seed: u32,
 }
 
 impl Terrain {
     pub fn new(size: u32) -> Terrain {
+        Terrain::with_seed(size, rand::thread_rng().gen())
+    }
+
+    /// Generates the same terrain every time for a given seed and size
+    pub fn with_seed(size: u32, seed: u32) -> Terrain {
         let map = vec![vec![0.0; size as usize]; size as usize];
 
-        println!("Generating terrain...");
+        let _terrain_span = tracing::info_span!("terrain", size, seed).entered();
 
+        let noise_span = tracing::info_span!("noise").entered();
+        tracing::info!("generating terrain");
         let t_generate = Instant::now();
         let elevation_join_handle: thread::JoinHandle<NoiseMap> = thread::spawn(move || {
-            let mut rng = rand::thread_rng();
-            let elevation_fbm = Fbm::<Perlin>::new(rng.gen());
+            let elevation_fbm = Fbm::<Perlin>::new(seed);
             PlaneMapBuilder::<Fbm<Perlin>, 2>::new(elevation_fbm)
                 .set_size(size as usize, size as usize)
                 .set_is_seamless(true)
@@ -56,8 +118,7 @@ impl Terrain {
         });
 
         let moisture_join_handle: thread::JoinHandle<NoiseMap> = thread::spawn(move || {
-            let mut rng = rand::thread_rng();
-            let moisture_fbm = Fbm::<Perlin>::new(rng.gen());
+            let moisture_fbm = Fbm::<Perlin>::new(seed.wrapping_add(1));
             PlaneMapBuilder::<Fbm<Perlin>, 2>::new(moisture_fbm)
                 .set_size(size as usize, size as usize)
                 .set_is_seamless(true)
@@ -68,7 +129,8 @@ impl Terrain {
 
         let elevation_map = elevation_join_handle.join().unwrap();
         let moisture_map = moisture_join_handle.join().unwrap();
-        println!("done - {:?}", t_generate.elapsed());
+        tracing::info!(elapsed = ?t_generate.elapsed(), "generated terrain");
+        drop(noise_span);
 
         fn calc_biome(elevation: f32, moisture: f32) -> TerrainType {
             if elevation < 0.1 {
@@ -116,27 +178,8 @@ impl Terrain {
             }
         }
 
-        fn get_terrain_color(terrain_type: &TerrainType) -> Rgb<u8> {
-            match terrain_type {
-                TerrainType::Ocean => Rgb([0, 153, 152]),
-                TerrainType::Beach => Rgb([153, 255, 255]),
-                TerrainType::Bare => Rgb([128, 128, 128]),
-                TerrainType::Grassland => Rgb([102, 255, 102]),
-                TerrainType::Scorched => Rgb([192, 192, 192]),
-                TerrainType::Shrubland => Rgb([204, 204, 0]),
-                TerrainType::Snow => Rgb([255, 255, 255]),
-                TerrainType::SubtropicalDesert => Rgb([255, 204, 153]),
-                TerrainType::Taiga => Rgb([0, 204, 102]),
-                TerrainType::TemperateDeciduousForest => Rgb([102, 204, 0]),
-                TerrainType::TemperateDesert => Rgb([255, 153, 51]),
-                TerrainType::TemperateRainForest => Rgb([0, 204, 0]),
-                TerrainType::TropicalRainForest => Rgb([0, 255, 0]),
-                TerrainType::TropicalSeasonalForest => Rgb([51, 255, 51]),
-                TerrainType::Tundra => Rgb([204, 229, 255]),
-            }
-        }
-
-        println!("Rendering images...");
+        let biome_span = tracing::info_span!("biomes").entered();
+        tracing::info!("classifying biomes");
         let t_render = Instant::now();
 
         let mut terrain_image: RgbImage = ImageBuffer::new(size, size);
@@ -153,6 +196,7 @@ impl Terrain {
             size,
             elevation_map: vec![],
             terrain_map: vec![],
+            seed,
         };
 
         for x in 0..size {
@@ -163,7 +207,7 @@ impl Terrain {
                 let moisture = get_map_value(&moisture_map, x, y);
 
                 let biome = calc_biome(elevation, moisture);
-                terrain.terrain_map.push(biome.clone() as u8);
+                terrain.terrain_map.push(biome as u8);
 
                 let terrain_pixel = terrain_image.get_pixel_mut(x, y);
                 *terrain_pixel = get_terrain_color(&biome);
@@ -177,10 +221,12 @@ impl Terrain {
                 *moisture_pixel = Rgb([moisture_val, moisture_val, moisture_val]);
             }
         }
-        println!("done - {:?}", t_render.elapsed());
+        tracing::info!(elapsed = ?t_render.elapsed(), "classified biomes");
+        drop(biome_span);
 
+        let _save_span = tracing::info_span!("save_images").entered();
+        tracing::info!("saving images");
         let t_write = Instant::now();
-        println!("Saving images...");
         let terrain_handle = thread::spawn(move || {
             terrain_image.save(Path::new("/tmp/terrain.png")).unwrap();
         });
@@ -197,7 +243,7 @@ impl Terrain {
         elevation_handle.join().unwrap();
         moisture_handle.join().unwrap();
 
-        println!("done - {:?}", t_write.elapsed());
+        tracing::info!(elapsed = ?t_write.elapsed(), "saved images");
 
         terrain
     }
@@ -205,7 +251,23 @@ impl Terrain {
     pub fn get_elevation(&self, x: u32, y: u32) -> f32 {
         let x = x.clamp(0, self.size - 1);
         let y = y.clamp(0, self.size - 1);
-        let idx = (self.size * y) + x;
+        let idx = (self.size * x) + y;
         self.elevation_map[idx as usize]
     }
+
+    pub fn get_terrain_type(&self, x: u32, y: u32) -> TerrainType {
+        let x = x.clamp(0, self.size - 1);
+        let y = y.clamp(0, self.size - 1);
+        let idx = (self.size * x) + y;
+        TerrainType::from(self.terrain_map[idx as usize])
+    }
+
+    /// Number of cells of each biome
+    pub fn biome_histogram(&self) -> HashMap<TerrainType, usize> {
+        let mut histogram = HashMap::new();
+        for cell in &self.terrain_map {
+            *histogram.entry(TerrainType::from(*cell)).or_insert(0) += 1;
+        }
+        histogram
+    }
 }
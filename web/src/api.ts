@@ -228,6 +228,92 @@ export class ErrorMessage {
   }
 }
 
+export class Damaged {
+  targetId: number;
+  sourceId: number;
+  amount: number;
+  health: number;
+
+  constructor(
+    targetId: number,
+    sourceId: number,
+    amount: number,
+    health: number,
+  ) {
+    this.targetId = targetId;
+    this.sourceId = sourceId;
+    this.amount = amount;
+    this.health = health;
+  }
+
+  static fromResponse(data: any) {
+    return new Damaged(data[0], data[1], data[2], data[3]);
+  }
+}
+
+export class Killed {
+  targetId: number;
+  killerId: number;
+
+  constructor(targetId: number, killerId: number) {
+    this.targetId = targetId;
+    this.killerId = killerId;
+  }
+
+  static fromResponse(data: any) {
+    return new Killed(data[0], data[1]);
+  }
+}
+
+export class Died {
+  killerId?: number;
+  respawnDelayMs: number;
+
+  constructor(killerId: number | undefined, respawnDelayMs: number) {
+    this.killerId = killerId;
+    this.respawnDelayMs = respawnDelayMs;
+  }
+
+  static fromResponse(data: any) {
+    return new Died(data[0] ?? undefined, data[1]);
+  }
+}
+
+export class Respawned {
+  objectId: number;
+
+  constructor(objectId: number) {
+    this.objectId = objectId;
+  }
+
+  static fromResponse(data: any) {
+    return new Respawned(data);
+  }
+}
+
+export class ChatMessage {
+  channel: any;
+  objectId: number;
+  username: string;
+  text: string;
+
+  constructor(
+    channel: any,
+    objectId: number,
+    username: string,
+    text: string,
+  ) {
+    this.channel = channel;
+    this.objectId = objectId;
+    this.username = username;
+    this.text = text;
+  }
+
+  static fromResponse(data: any) {
+    return new ChatMessage(data[0], data[1], data[2], data[3]);
+  }
+}
+
 const decoders = {
   StateUpdate: (data: any) => StateUpdate.fromResponse(data),
   Pong: (data: any) => Pong.fromResponse(data),
@@ -235,12 +321,32 @@ const decoders = {
   Error: (data: any) => ErrorMessage.fromResponse(data),
   TerrainMap: (data: any) => TerrainMap.fromResponse(data),
   ElevationMap: (data: any) => ElevationMap.fromResponse(data),
+  Damaged: (data: any) => Damaged.fromResponse(data),
+  Killed: (data: any) => Killed.fromResponse(data),
+  Died: (data: any) => Died.fromResponse(data),
+  Respawned: (data: any) => Respawned.fromResponse(data),
+  ChatMessage: (data: any) => ChatMessage.fromResponse(data),
+  // not used by the client yet, handed to handlers as sent
+  Inventory: (data: any) => data,
+  RoundStarted: (data: any) => data,
+  WaveStarted: (data: any) => data,
+  RoundEnded: (data: any) => data,
+  RoundStateChanged: (data: any) => data,
+  Scoreboard: (data: any) => data,
+  Leaderboard: (data: any) => data,
+  HelloRejected: (data: any) => data,
+  Goodbye: (data: any) => data,
+  RespawnRejected: (data: any) => data,
 } as { [key: string]: any };
 
 const decodeResponse = (data: { [key: string]: any }) => {
   const rv = {} as { [key: string]: any };
   for (let k in data) {
     const decoder = decoders[k];
+    if (!decoder) {
+      console.log("ignoring unknown response", k);
+      continue;
+    }
     rv[k] = decoder(data[k]);
   }
   return rv;